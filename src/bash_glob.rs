@@ -1,38 +1,90 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use crate::core::shopts::Shopts;
+
+#[derive(Debug, Default)]
+pub struct GlobOptions {
+    pub extglob: bool,
+    pub dotglob: bool,
+    pub nocase: bool,
+    pub globstar: bool,
+}
+
+impl GlobOptions {
+    pub fn new(shopts: &Shopts) -> GlobOptions {
+        GlobOptions {
+            extglob: shopts.get("extglob"),
+            dotglob: shopts.get("dotglob"),
+            nocase: shopts.get("nocaseglob"),
+            globstar: shopts.get("globstar"),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct PatternElem {
     pub asterisk: bool,
     pub question: bool,
     pub inv: bool,
-    pub chars: Vec<char>, 
-    pub ranges: Vec<(char, char)>, 
+    pub chars: Vec<char>,
+    pub ranges: Vec<(char, char)>,
+    pub classes: Vec<String>,
 }
 
-pub fn judge(s: &String, pos: usize, pe: &PatternElem) -> Vec<usize> {
+fn class_match(class: &str, c: char) -> bool {
+    match class {
+        "alnum"  => c.is_alphanumeric(),
+        "alpha"  => c.is_alphabetic(),
+        "ascii"  => c.is_ascii(),
+        "blank"  => c == ' ' || c == '\t',
+        "cntrl"  => c.is_control(),
+        "digit"  => c.is_ascii_digit(),
+        "graph"  => ! c.is_control() && ! c.is_whitespace(),
+        "lower"  => c.is_lowercase(),
+        "print"  => ! c.is_control(),
+        "punct"  => c.is_ascii_punctuation(),
+        "space"  => c.is_whitespace(),
+        "upper"  => c.is_uppercase(),
+        "word"   => c.is_alphanumeric() || c == '_',
+        "xdigit" => c.is_ascii_hexdigit(),
+        _        => false,
+    }
+}
+
+pub fn judge(s: &[char], pos: usize, pe: &PatternElem, nocase: bool) -> Vec<usize> {
     let mut ans = vec![];
     if pe.asterisk {
-        for n in pos..s.chars().count()+1 {
+        for n in pos..s.len()+1 {
             ans.push(n);
         }
 
         return ans;
     }
 
+    if pos >= s.len() {
+        return ans;
+    }
+
     if pe.question {
         return vec!(pos+1);
     }
 
-    if let Some(c) = s.chars().nth(pos) {
-        let matched = pe.chars.iter().any(|ch| ch == &c) 
-                      || pe.ranges.iter().any(|r| r.0 < c && c < r.1);
+    let mut cs = vec![s[pos]];
+    if nocase {
+        cs.extend(s[pos].to_lowercase());
+        cs.extend(s[pos].to_uppercase());
+    }
+    let matched = cs.iter().any(|&c| pe.chars.iter().any(|ch| ch == &c)
+                                     || pe.ranges.iter().any(|r| r.0 <= c && c <= r.1) )
+                  || pe.classes.iter().any(|cls| class_match(cls, s[pos]));
 
-        if (pe.inv && ! matched) || (!pe.inv && matched) {
-             ans.push(pos+1);
-        }
+    if (pe.inv && ! matched) || (!pe.inv && matched) {
+         ans.push(pos+1);
     }
 
     ans
@@ -45,34 +97,7 @@ fn wildcard() -> PatternElem {
         inv: false,
         chars: vec![],
         ranges: vec![],
-    }
-}
-
-fn bracket(chs: &Vec<char>) -> PatternElem {
-    let inv = chs[0] == '^' || chs[0] == '!';
-
-    let chars = if inv {
-        chs[1..].to_vec().clone()
-    }else{
-        chs.clone()
-    };
-
-    let mut chars2 = vec![];
-    let mut ranges = vec![];
-    for (i, c) in chars.iter().enumerate() {
-        if c == &'-' && i >= 1 && i+1 < chars.len() {
-            ranges.push((chars[i-1], chars[i+1]));
-        }else{
-            chars2.push(*c);
-        }
-    }
-
-    return PatternElem {
-        asterisk: false,
-        question: false,
-        inv: inv,
-        chars: chars2,
-        ranges: ranges,
+        classes: vec![],
     }
 }
 
@@ -83,6 +108,7 @@ fn anychar() -> PatternElem {
         inv: false,
         chars: vec![],
         ranges: vec![],
+        classes: vec![],
     }
 }
 
@@ -93,72 +119,184 @@ fn simple_char(c: char) -> PatternElem {
         inv: false,
         chars: vec!(c),
         ranges: vec![],
+        classes: vec![],
     }
 }
 
-fn set_glob(glob: &String) -> Vec<PatternElem> {
-    let mut ans = vec![];
-    let mut pos = 0;
-    let mut escaped = false;
-    let mut in_brace = false;
-    let mut bracket_str = vec![];
+enum BracketItem {
+    Char(char, bool), // (char, escaped)
+    Class(String),
+}
+
+/* [:class:], [=c=] or [.c.] at pos; returns the item and the position after it */
+fn bracket_expression(glob: &[char], pos: usize) -> Option<(BracketItem, usize)> {
+    if pos+1 >= glob.len() || glob[pos] != '[' {
+        return None;
+    }
+    let kind = glob[pos+1];
+    if kind != ':' && kind != '=' && kind != '.' {
+        return None;
+    }
+
+    let mut end = pos + 2;
+    while end+1 < glob.len() {
+        if glob[end] == kind && glob[end+1] == ']' {
+            let inner: String = glob[pos+2..end].iter().collect();
+            let item = match (kind, inner.chars().count()) {
+                (':', _) => BracketItem::Class(inner),
+                (_, 1)   => BracketItem::Char(inner.chars().next().unwrap(), true),
+                _        => BracketItem::Class(String::new()), // unknown symbols match nothing
+            };
+            return Some( (item, end+2) );
+        }
+        end += 1;
+    }
+    None
+}
 
+/* returns the element and the position after "]", or None if the bracket is not closed */
+fn bracket(glob: &[char], start: usize) -> Option<(PatternElem, usize)> {
+    let mut pos = start + 1;
+    let inv = pos < glob.len() && (glob[pos] == '!' || glob[pos] == '^');
+    if inv {
+        pos += 1;
+    }
+
+    let mut chars = vec![];
+    let mut first = true;
     loop {
-        if glob.chars().count() == pos {
+        if pos >= glob.len() {
+            return None;
+        }
+
+        let ch = glob[pos];
+        if ch == ']' && ! first {
             break;
         }
+        first = false;
 
-        let ch = if let Some(c) = glob.chars().nth(pos) {
-            c
+        if let Some((item, next)) = bracket_expression(glob, pos) {
+            chars.push(item);
+            pos = next;
+        }else if ch == '\\' && pos+1 < glob.len() {
+            chars.push( BracketItem::Char(glob[pos+1], true) );
+            pos += 2;
         }else{
-            panic!("Glob parse error");
-        };
+            chars.push( BracketItem::Char(ch, false) );
+            pos += 1;
+        }
+    }
 
-        pos += 1;
+    let mut elem = PatternElem {
+        asterisk: false,
+        question: false,
+        inv,
+        chars: vec![],
+        ranges: vec![],
+        classes: vec![],
+    };
 
-        if ! escaped && ch == '\\' {
-            escaped = true;
+    let mut i = 0;
+    while i < chars.len() {
+        if i+2 < chars.len() {
+            if let (BracketItem::Char(from, _), BracketItem::Char('-', false), BracketItem::Char(to, _))
+                  = (&chars[i], &chars[i+1], &chars[i+2]) {
+                elem.ranges.push( (*from, *to) );
+                i += 3;
+                continue;
+            }
+        }
+
+        match &chars[i] {
+            BracketItem::Char(c, _) => elem.chars.push(*c),
+            BracketItem::Class(cls) => elem.classes.push(cls.clone()),
+        }
+        i += 1;
+    }
+
+    Some( (elem, pos+1) )
+}
+
+pub enum GlobElem {
+    Normal(PatternElem),
+    Ext(char, Vec<Vec<GlobElem>>), // ?(..), *(..), +(..), @(..) or !(..) with the alternatives
+}
+
+/* parses alternatives until ")" in an extglob, or the whole pattern otherwise */
+fn set_glob_elems(glob: &[char], pos: &mut usize, extglob: bool, in_ext: bool) -> Option<Vec<Vec<GlobElem>>> {
+    let mut alts = vec![vec![]];
+
+    while *pos < glob.len() {
+        let ch = glob[*pos];
+
+        if in_ext && ch == ')' {
+            *pos += 1;
+            return Some(alts);
+        }
+        if in_ext && ch == '|' {
+            alts.push(vec![]);
+            *pos += 1;
             continue;
         }
 
-        if escaped {
-            ans.push(simple_char(ch));
-        }else if ch == '*' {
-            ans.push(wildcard());
-        }else if ch == '?' {
-            ans.push(anychar());
-        }else if ch == '[' && ! in_brace {
-            in_brace = true;
-        }else if ch == ']' && in_brace {
-            ans.push(bracket(&bracket_str));
-            in_brace = false;
-            bracket_str = vec![];
-        }else if in_brace {
-            bracket_str.push(ch);
-        }else{
-            ans.push(simple_char(ch));
+        let elems = alts.last_mut().unwrap();
+
+        if ch == '\\' && *pos+1 < glob.len() {
+            elems.push(GlobElem::Normal(simple_char(glob[*pos+1])));
+            *pos += 2;
+            continue;
         }
 
-        escaped = false;
+        if extglob && "?*+@!".contains(ch) && *pos+1 < glob.len() && glob[*pos+1] == '(' {
+            let mut next = *pos + 2;
+            if let Some(inner) = set_glob_elems(glob, &mut next, extglob, true) {
+                elems.push(GlobElem::Ext(ch, inner));
+                *pos = next;
+                continue;
+            }
+        }
+
+        if ch == '[' {
+            if let Some((elem, next)) = bracket(glob, *pos) {
+                elems.push(GlobElem::Normal(elem));
+                *pos = next;
+                continue;
+            }
+        }
+
+        match ch {
+            '*' => elems.push(GlobElem::Normal(wildcard())),
+            '?' => elems.push(GlobElem::Normal(anychar())),
+            _   => elems.push(GlobElem::Normal(simple_char(ch))),
+        }
+        *pos += 1;
     }
 
-    for ch in bracket_str {
-        ans.push(simple_char(ch));
+    match in_ext {
+        true  => None, // not closed
+        false => Some(alts),
     }
+}
 
-    ans
+fn set_glob(glob: &str, extglob: bool) -> Vec<GlobElem> {
+    let glob: Vec<char> = glob.chars().collect();
+    let mut pos = 0;
+    match set_glob_elems(&glob, &mut pos, extglob, false) {
+        Some(mut alts) => alts.remove(0),
+        None           => vec![],
+    }
 }
 
-pub fn glob_match(glob: &String, s: &String) -> bool {
-    let pattern = set_glob(glob);
-    let mut poss = HashSet::new();
-    poss.insert(0);
+/* returns all the positions where matches that begin at one of the starts end */
+fn match_elems(elems: &[GlobElem], s: &[char], starts: HashSet<usize>, nocase: bool) -> HashSet<usize> {
+    let mut poss = starts;
 
-    for pat in pattern {
+    for elem in elems {
         let mut poss_new = HashSet::new();
         for p in poss {
-            for n in judge(s, p, &pat) {
-                poss_new.insert(n);
+            match elem {
+                GlobElem::Normal(pe)      => poss_new.extend(judge(s, p, pe, nocase)),
+                GlobElem::Ext(kind, alts) => poss_new.extend(match_ext(*kind, alts, s, p, nocase)),
             }
         }
         poss = poss_new;
@@ -167,5 +305,217 @@ pub fn glob_match(glob: &String, s: &String) -> bool {
         }
     }
 
-    ! poss.insert(s.chars().count())
+    poss
+}
+
+fn match_alts(alts: &[Vec<GlobElem>], s: &[char], starts: &HashSet<usize>, nocase: bool) -> HashSet<usize> {
+    alts.iter().flat_map(|a| match_elems(a, s, starts.clone(), nocase)).collect()
+}
+
+fn match_ext(kind: char, alts: &[Vec<GlobElem>], s: &[char], pos: usize, nocase: bool) -> HashSet<usize> {
+    let start = HashSet::from([pos]);
+
+    match kind {
+        '@' => match_alts(alts, s, &start, nocase),
+        '?' => {
+            let mut ans = match_alts(alts, s, &start, nocase);
+            ans.insert(pos);
+            ans
+        },
+        '*' | '+' => {
+            let mut ans = if kind == '*' { start.clone() }else{ HashSet::new() };
+            let mut frontier = start;
+            loop { // repeats from newly reached positions only
+                let found: HashSet<usize> = match_alts(alts, s, &frontier, nocase)
+                                            .difference(&ans).cloned().collect();
+                if found.is_empty() {
+                    return ans;
+                }
+                ans.extend(found.iter());
+                frontier = found;
+            }
+        },
+        _ => {
+            let ng = match_alts(alts, s, &start, nocase);
+            (pos..s.len()+1).filter(|e| ! ng.contains(e)).collect()
+        },
+    }
+}
+
+pub fn glob_match(glob: &str, s: &str, opts: &GlobOptions) -> bool {
+    let pattern = set_glob(glob, opts.extglob);
+    let s: Vec<char> = s.chars().collect();
+    match_elems(&pattern, &s, HashSet::from([0]), opts.nocase).contains(&s.len())
+}
+
+/* matching in which no wildcard matches "/" such as for GLOBIGNORE */
+pub fn glob_match_path(glob: &str, s: &str, opts: &GlobOptions) -> bool {
+    let globs: Vec<&str> = glob.split('/').collect();
+    let comps: Vec<&str> = s.split('/').collect();
+    globs.len() == comps.len()
+    && globs.iter().zip(comps.iter())
+            .all(|(g, c)| glob_match(g, c, opts))
+}
+
+/* true if the pattern has an unescaped *, ?, [ or the start of an extglob */
+pub fn has_glob_chars(s: &str) -> bool {
+    let mut escaped = false;
+    let mut prev = ' ';
+
+    for ch in s.chars() {
+        if escaped {
+            escaped = false;
+            prev = ' ';
+            continue;
+        }else if ch == '\\' {
+            escaped = true;
+            continue;
+        }
+
+        if ch == '*' || ch == '[' || ch == '?' || (ch == '(' && "+@!".contains(prev)) {
+            return true;
+        }
+        prev = ch;
+    }
+    false
+}
+
+fn unescape(s: &str) -> String {
+    let mut ans = String::new();
+    let mut escaped = false;
+    for ch in s.chars() {
+        if ! escaped && ch == '\\' {
+            escaped = true;
+            continue;
+        }
+        ans.push(ch);
+        escaped = false;
+    }
+    ans
+}
+
+fn split_path(pattern: &str) -> Vec<String> {
+    let mut ans = vec![String::new()];
+    let mut escaped = false;
+    for ch in pattern.chars() {
+        if ch == '/' && ! escaped {
+            ans.push(String::new());
+        }else if let Some(last) = ans.last_mut() {
+            last.push(ch);
+        }
+        escaped = ! escaped && ch == '\\';
+    }
+    ans
+}
+
+fn match_entries(base: &str, comp: &str, dir_only: bool, opts: &GlobOptions) -> Vec<String> {
+    let dir = if base.is_empty() { "." }else{ base };
+    let entries = match fs::read_dir(dir) {
+        Ok(es) => es,
+        Err(_) => return vec![],
+    };
+
+    let hidden_ok = opts.dotglob || comp.starts_with(".") || comp.starts_with("\\.");
+    let mut ans = vec![];
+    for e in entries.flatten() {
+        let name = e.file_name().to_string_lossy().to_string();
+        if name.starts_with(".") && ! hidden_ok {
+            continue;
+        }
+        if ! glob_match(comp, &name, opts) {
+            continue;
+        }
+
+        let path = format!("{}{}", base, name);
+        if ! dir_only || Path::new(&path).is_dir() {
+            ans.push(path);
+        }
+    }
+    ans
+}
+
+/* all the files and directories under base for "**"; symbolic links are not followed */
+fn globstar_entries(base: &str, dir_only: bool, opts: &GlobOptions) -> Vec<String> {
+    let dir = if base.is_empty() { "." }else{ base };
+    let entries = match fs::read_dir(dir) {
+        Ok(es) => es,
+        Err(_) => return vec![],
+    };
+
+    let mut ans = vec![];
+    for e in entries.flatten() {
+        let name = e.file_name().to_string_lossy().to_string();
+        if name.starts_with(".") && ! opts.dotglob {
+            continue;
+        }
+
+        let path = format!("{}{}", base, name);
+        let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if is_dir {
+            ans.push(path.clone());
+            ans.append(&mut globstar_entries(&(path + "/"), dir_only, opts));
+        }else if ! dir_only {
+            ans.push(path);
+        }
+    }
+    ans
+}
+
+/* pathname expansion of a pattern in the escaped form; results are not sorted */
+pub fn expand_path(pattern: &str, opts: &GlobOptions) -> Vec<String> {
+    let comps = split_path(pattern);
+    let mut bases = vec![String::new()]; // directories with the trailing slash except the first one
+    let mut ans = vec![];
+    let mut globbed = false;
+
+    for (i, comp) in comps.iter().enumerate() {
+        let last = i+1 == comps.len();
+        if globbed && ! last && comp.is_empty() { // slashes after a glob are squeezed like bash
+            continue;
+        }
+        globbed |= has_glob_chars(comp);
+
+        let mut found = vec![];
+        let mut next_bases = vec![];
+        for base in &bases {
+            if opts.globstar && comp == "**" {
+                match last { // "**" also matches zero directories
+                    true  => if ! base.is_empty() { found.push(base.clone()) },
+                    false => next_bases.push(base.clone()),
+                }
+                found.append(&mut globstar_entries(base, ! last, opts));
+            }else if ! has_glob_chars(comp) {
+                found.push(base.clone() + &unescape(comp));
+            }else{
+                found.append(&mut match_entries(base, comp, ! last, opts));
+            }
+        }
+
+        if last {
+            ans = found;
+        }else{
+            next_bases.extend(found.into_iter().map(|f| f + "/"));
+            bases = next_bases;
+        }
+    }
+
+    ans.into_iter()
+        .filter(|c| fs::symlink_metadata(c).is_ok() || (c.ends_with("/") && Path::new(c).is_dir()))
+        .collect()
+}
+
+/* byte order in the C locale, otherwise a dictionary order like other locales */
+pub fn collate(a: &str, b: &str, locale: &str) -> Ordering {
+    if locale.is_empty() || locale == "C" || locale == "POSIX" || locale.starts_with("C.") {
+        return a.cmp(b);
+    }
+
+    let key = |s: &str| s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect::<String>();
+
+    key(a).cmp(&key(b))
+        .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+        .then_with(|| b.cmp(a)) // lower case first
 }
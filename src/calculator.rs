@@ -8,12 +8,12 @@ fn op_order(operator: &String) -> u8 {
 
     match op {
         "**" => 5,
-        "*" | "/" | "%"            => 6, 
-        "+" | "-"                  => 7, 
-        "<<" | ">>"                => 8, 
-        "<=" | ">=" | ">" | "<"    => 9, 
-        "(" | ")"                  => 20, 
-        _ => 255, 
+        "*" | "/" | "%"            => 6,
+        "+" | "-"                  => 7,
+        "<<" | ">>"                => 8,
+        "<=" | ">=" | ">" | "<"    => 9,
+        "(" | ")"                  => 20,
+        _ => 255,
     }
 }
 
@@ -27,107 +27,173 @@ fn get_integer(text: &mut Feeder) -> Option<(String,u8)> {
     }
 }
 
+/* a variable is replaced with its value, which is also evaluated as an expression */
+fn get_variable(text: &mut Feeder, core: &mut ShellCore, depth: usize) -> Result<Option<(String,u8)>, String> {
+    let backup = text.clone();
+    let pos = if text.starts_with("$") {
+        text.consume(1);
+        text.scanner_name_or_parameter()
+    }else{
+        text.scanner_name()
+    };
+    if pos == 0 {
+        text.rewind(backup);
+        return Ok(None);
+    }
+
+    let name = text.consume(pos);
+    let value = core.get_var(&name);
+    if value.trim().is_empty() {
+        return Ok(Some( ("0".to_string(), 0) ));
+    }
+    if value.trim().parse::<i64>().is_ok() {
+        return Ok(Some( (value.trim().to_string(), 0) ));
+    }
+
+    if depth > 64 {
+        return Err(format!("{}: expression recursion level exceeded", name));
+    }
+    Ok(Some( (evaluate(&value, core, depth+1)?.to_string(), 0) ))
+}
+
 fn get_operator(text: &mut Feeder) -> Option<(String,u8)> {
     if text.len() == 0 {
         return None;
     }
 
-    if let Some(_) = "+-/%*".find(text.nth(0)) {
-        let op = text.consume(1);
+    let len = if text.starts_with("**") { 2 }else{ 1 };
+    if "+-/%*)".find(text.nth(0)).is_some() {
+        let op = text.consume(len);
         Some( (op.clone(), op_order(&op)) )
     }else{
         None
     }
 }
 
-fn reduce(stack: &mut Vec<i32>, op: String ) {
-    let op: &str = &op.clone();
-
-    let right = stack.pop().unwrap();
-    let left = stack.pop().unwrap();
-
-    match op {
-        "+" => stack.push(left+right),
-        "-" => stack.push(left-right),
-        "*" => stack.push(left*right),
-        "/" => stack.push(left/right),
-        _ => (), 
-    }
+fn reduce(stack: &mut Vec<i64>, op: &str) -> Result<(), String> {
+    let (right, left) = match (stack.pop(), stack.pop()) {
+        (Some(r), Some(l)) => (r, l),
+        _ => return Err("syntax error: operand expected".to_string()),
+    };
+
+    let ans = match op {
+        "+"  => left.wrapping_add(right),
+        "-"  => left.wrapping_sub(right),
+        "*"  => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => return Err("division by 0".to_string()),
+        "/"  => left.wrapping_div(right),
+        "%"  => left.wrapping_rem(right),
+        "**" if right < 0 => return Err("exponent less than 0".to_string()),
+        "**" => left.wrapping_pow(right as u32),
+        _    => return Err(format!("syntax error: invalid arithmetic operator (error token is \"{}\")", op)),
+    };
+    stack.push(ans);
+    Ok(())
 }
 
-pub fn calculate(expression: String, core: &mut ShellCore) -> String {
-    let tokens = tokenizer(expression, core);
-    let mut num_stack: Vec<i32> = vec![];
-    let mut wait_stack: Vec<(String,u8)> = vec![];
-
-    //eprintln!("TOKENS: {:?}", tokens);
+fn evaluate(expression: &str, core: &mut ShellCore, depth: usize) -> Result<i64, String> {
+    let tokens = tokenizer(expression, core, depth)?;
+    let mut num_stack: Vec<i64> = vec![];
+    let mut op_stack: Vec<(String,u8)> = vec![];
 
     for t in tokens {
-     //   eprintln!("STACK: {:?}", num_stack);
-      //  eprintln!("WAIT STACK: {:?}", wait_stack);
-        while wait_stack.len() != 0 {
-            let wtop = wait_stack.pop().unwrap();
-
-            if t.0 == ")" && wtop.0 == "(" {
-            //    wait_stack.push(wtop);
-                break;
-            }
-
-            if wtop.1 <= t.1 {
-                if wtop.1 > 0 {
-                    reduce(&mut num_stack, wtop.0.clone());
-                }else{
-                    num_stack.push(wtop.0.parse::<i32>().unwrap());
+        match t.0.as_str() {
+            "(" => op_stack.push(t),
+            ")" => loop {
+                match op_stack.pop() {
+                    Some(op) if op.0 == "(" => break,
+                    Some(op) => reduce(&mut num_stack, &op.0)?,
+                    None     => return Err("syntax error: unbalanced parenthesis".to_string()),
                 }
-            }else{
-                wait_stack.push(wtop);
-                break;
-            }
+            },
+            _ if t.1 == 0 => num_stack.push(t.0.parse::<i64>().map_err(|e| e.to_string())?),
+            _ => {
+                while let Some(top) = op_stack.last() {
+                    /* "**" is right associative */
+                    if top.0 == "(" || top.1 > t.1 || (top.1 == t.1 && t.0 == "**") {
+                        break;
+                    }
+                    let op = op_stack.pop().unwrap();
+                    reduce(&mut num_stack, &op.0)?;
+                }
+                op_stack.push(t);
+            },
         }
+    }
 
-        if t.0 != ")" {
-            wait_stack.push(t);
+    while let Some(op) = op_stack.pop() {
+        if op.0 == "(" {
+            return Err("syntax error: unbalanced parenthesis".to_string());
         }
+        reduce(&mut num_stack, &op.0)?;
     }
 
-//    eprintln!("-------------------");
-    while wait_stack.len() != 0 {
-        let wtop = wait_stack.pop().unwrap();
-        if wtop.1 > 0 {
-            reduce(&mut num_stack, wtop.0.clone());
-        }else{
-            num_stack.push(wtop.0.parse::<i32>().unwrap());
-        //    stack.push(wtop);
-        }
- //       eprintln!("STACK: {:?}", num_stack);
-  //      eprintln!("WAIT STACK: {:?}", wait_stack);
+    match (num_stack.pop(), num_stack.len()) {
+        (Some(n), 0) => Ok(n),
+        (None, _)    => Ok(0),
+        _            => Err("syntax error in expression".to_string()),
     }
+}
 
-    //stack.iter().map(|t| t.0.clone()).collect::<Vec<String>>().join(" ")
-    num_stack.pop().unwrap().to_string()
+pub fn calculate(expression: String, core: &mut ShellCore) -> String {
+    try_calculate(&expression, core).unwrap_or("0".to_string())
 }
 
-fn tokenizer(expression: String, _core: &mut ShellCore) -> Vec<(String,u8)> {
-    //let mut stack = vec![];
+/* the error is reported and None is returned */
+pub fn try_calculate(expression: &str, core: &mut ShellCore) -> Option<String> {
+    match evaluate(expression, core, 0) {
+        Ok(n)  => Some(n.to_string()),
+        Err(e) => {
+            eprintln!("bash: {}: {}", expression.trim(), e);
+            None
+        },
+    }
+}
+
+fn tokenizer(expression: &str, core: &mut ShellCore, depth: usize) -> Result<Vec<(String,u8)>, String> {
     let mut tokens = vec![];
-    
-    let mut text = Feeder::new_from(expression.clone());
-    while text.len() != 0 {
+
+    let mut text = Feeder::new_from(expression.to_string());
+    loop {
         //get value
+        text.consume_blank();
+        while text.starts_with("(") {
+            tokens.push( (text.consume(1), op_order(&"(".to_string())) );
+            text.consume_blank();
+        }
+
         if let Some(n) = get_integer(&mut text) {
             tokens.push(n);
-        }else{
+        }else if text.starts_with("-") { // unary minus: multiplication by -1 prior to the others
+            text.consume(1);
+            tokens.push( ("-1".to_string(), 0) );
+            tokens.push( ("*".to_string(), op_order(&"**".to_string())) );
+            continue;
+        }else if let Some(n) = get_variable(&mut text, core, depth)? {
+            tokens.push(n);
+        }else if text.len() == 0 && tokens.is_empty() {
             break;
+        }else{
+            return Err(format!("syntax error: operand expected (error token is \"{}\")", text.consume(text.len())));
         }
 
         //get operator
-        if let Some(op) = get_operator(&mut text) {
-            tokens.push(op);
-        }else{
-            break;
+        text.consume_blank();
+        loop {
+            match get_operator(&mut text) {
+                Some(op) if op.0 == ")" => {
+                    tokens.push(op);
+                    text.consume_blank();
+                },
+                Some(op) => {
+                    tokens.push(op);
+                    break;
+                },
+                None if text.len() == 0 => return Ok(tokens),
+                None => return Err(format!("syntax error in expression (error token is \"{}\")", text.consume(text.len()))),
+            }
         }
     }
 
-    tokens
+    Ok(tokens)
 }
-
@@ -2,70 +2,390 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 pub mod builtins;
+pub mod hash;
 pub mod shopts;
 pub mod jobs;
 pub mod job;
 pub mod proc;
+pub mod variables;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::rc::Rc;
+use std::cell::Cell;
 use std::fs::File;
+use std::io::Read;
 use std::env;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use crate::core::shopts::Shopts;
 use nix::sys::wait::{waitpid, WaitStatus, WaitPidFlag};
 use nix::unistd::Pid;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::resource::{getrlimit, Resource};
+use nix::libc::RLIM_INFINITY;
 use crate::core::jobs::Jobs;
+use crate::core::hash::CommandHash;
+use crate::core::variables::{Variables, VarValue, Scope};
 
-use nix::unistd::read;
+use nix::unistd::{close, read, write};
 use std::os::unix::prelude::RawFd;
+use crate::Feeder;
+use crate::elements::word::Word;
+use crate::calculator::{calculate, try_calculate};
+use crate::elements::substitution::Assignment;
+use crate::elements::command::Command;
+use crate::elements::command::function_definition::{FunctionDefinition, ShellFunction};
+use std::process;
+
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub name: String, // function name, "source" or "main"
+    pub source: String,
+    pub lineno: u32, // line number of the call
+    pub args: Vec<String>,
+}
+
+impl CallFrame {
+    pub fn new(name: &str, source: &str, lineno: u32, args: &[String]) -> CallFrame {
+        CallFrame {
+            name: name.to_string(),
+            source: source.to_string(),
+            lineno,
+            args: args.to_vec(),
+        }
+    }
+}
 
 pub struct ShellCore {
     pub builtins: HashMap<String, fn(&mut ShellCore, args: &mut Vec<String>) -> i32>,
-    pub functions: HashMap<String, String>,
-    pub arrays: HashMap<String, Vec<String>>,
-    pub vars: HashMap<String, String>,
+    pub functions: HashMap<String, Rc<ShellFunction>>,
+    pub exported_functions: HashSet<String>,
+    pub vars: Variables,
     pub args: Vec<String>,
     pub aliases: HashMap<String, String>,
     pub history: Vec<String>,
     pub flags: String,
     pub jobs: Jobs, //old
+    pub hash: CommandHash,
     pub in_double_quot: bool,
     pub pipeline_end: String,
     pub script_file: Option<File>,
-    pub return_enable: bool,
     pub return_flag: bool,
     pub shopts: Shopts, 
     pub nest: Vec<String>, 
+    pub subshell_level: usize,
+    pub comsub_level: usize, // depth of command substitutions for the xtrace prefix
+    pub call_stack: Vec<CallFrame>,
+    pub lineno: u32,
+    random_seed: Cell<u32>,
+    seconds_base: Instant,
+    pub discard_flag: bool, // set on a fatal error to abandon the current command line
+    pub syntax_error: bool,
+    pub eval_level: usize,
+    pub loop_level: u32,
+    pub break_counter: u32, // number of loops left to break out of
+    stack_base: usize,
+    stack_limit: usize,
 }
 
+const DYNAMIC_VARS: [&str; 9] = ["RANDOM", "SRANDOM", "SECONDS", "EPOCHSECONDS",
+                                  "EPOCHREALTIME", "BASHPID", "BASH_SUBSHELL", "HISTCMD", "LINENO"];
+
 impl ShellCore {
     pub fn new() -> ShellCore {
         let mut conf = ShellCore{
             builtins: HashMap::new(),
             functions: HashMap::new(),
-            arrays: HashMap::new(),
-            vars: HashMap::new(),
+            exported_functions: HashSet::new(),
+            vars: Variables::new(),
             args: vec![],
             aliases: HashMap::new(),
             history: Vec::new(),
             flags: String::new(),
+            hash: CommandHash::new(),
             jobs: Jobs::new(),// {backgrounds: vec!(Job::new(&"".to_string(), &vec![], false))},
             in_double_quot: false,
             pipeline_end: String::new(),
             script_file: None,
             return_flag: false,
-            return_enable: false,
             shopts: Shopts::new(),
             nest: vec![],
+            subshell_level: 0,
+            comsub_level: 0,
+            call_stack: vec![CallFrame::new("main", "", 0, &[])],
+            lineno: 0,
+            random_seed: Cell::new(process::id() ^ SystemTime::now().duration_since(UNIX_EPOCH)
+                                   .map(|d| d.subsec_nanos()).unwrap_or(0)),
+            seconds_base: Instant::now(),
+            discard_flag: false,
+            syntax_error: false,
+            eval_level: 0,
+            loop_level: 0,
+            break_counter: 0,
+            stack_base: 0,
+            stack_limit: 0,
+        };
+
+        let base = 0u8;
+        conf.stack_base = &base as *const u8 as usize;
+        conf.stack_limit = match getrlimit(Resource::RLIMIT_STACK) {
+            Ok((soft, _)) if soft != RLIM_INFINITY => soft as usize / 4 * 3,
+            _ => 6*1024*1024,
         };
 
+        let mut funcs = vec![];
+        for (k, v) in env::vars() {
+            if k.starts_with("BASH_FUNC_") && k.ends_with("%%") {
+                funcs.push( (k[10..k.len()-2].to_string(), v) );
+                continue;
+            }
+            let var = conf.vars.entry(&k, &Scope::Global);
+            var.value = Some(VarValue::Scalar(v));
+            var.set_attr('x', true);
+        }
         conf.set_var("?", &0.to_string());
         builtins::set_builtins(&mut conf);
 
+        /* the values are computed in get_var while the variables are not unset */
+        for name in DYNAMIC_VARS {
+            conf.vars.entry(name, &Scope::Global).value = Some(VarValue::Scalar(String::new()));
+        }
+
+        for (name, def) in funcs {
+            conf.import_function(&name, &def);
+        }
+
         conf
     }
 
-    pub fn set_var(&mut self, key: &str, value: &str) {
-        self.vars.insert(key.to_string(), value.to_string());
+    pub fn set_var(&mut self, key: &str, value: &str) -> bool {
+        self.set_value(key, VarValue::Scalar(value.to_string()))
+    }
+
+    /* follows namerefs and returns the referred name with the subscript if the reference is "name[i]",
+       or an empty name for a circular reference */
+    pub fn resolve_nameref(&self, key: &str) -> (String, Option<String>) {
+        let mut name = key.to_string();
+        let mut visited = vec![];
+        loop {
+            let target = match self.vars.get(&name) {
+                Some(v) if v.has_attr('n') => v.scalar().unwrap_or_default(),
+                _ => return (name, None),
+            };
+            if target.is_empty() {
+                return (name, None);
+            }
+
+            if visited.contains(&name) {
+                eprintln!("bash: warning: {}: circular name reference", key);
+                return (String::new(), None);
+            }
+            visited.push(name.clone());
+
+            match target.split_once('[') {
+                Some((n, i)) if i.ends_with(']') => return (n.to_string(), Some(i[..i.len()-1].to_string())),
+                _ => name = target,
+            }
+        }
+    }
+
+    /* applies the integer and case attributes to an assigned value, None on an arithmetic error */
+    fn attr_value(&mut self, key: &str, value: &str) -> Option<String> {
+        let attrs = match self.vars.get(key) {
+            Some(v) => v.attrs.clone(),
+            None    => return Some(value.to_string()),
+        };
+
+        let mut ans = value.to_string();
+        if attrs.contains('i') {
+            ans = try_calculate(&ans, self)?;
+        }
+        if attrs.contains('l') {
+            ans = ans.to_lowercase();
+        }else if attrs.contains('u') {
+            ans = ans.to_uppercase();
+        }
+        Some(ans)
+    }
+
+    pub fn set_value(&mut self, key: &str, value: VarValue) -> bool {
+        let (key, index) = self.resolve_nameref(key);
+        let key = key.as_str();
+        if key.is_empty() {
+            return false;
+        }
+        if let (Some(i), VarValue::Scalar(s)) = (&index, &value) {
+            return self.set_element(key, i, s);
+        }
+
+        if let Some(v) = self.vars.get(key) {
+            if v.has_attr('r') {
+                eprintln!("bash: {}: readonly variable", key);
+                return false;
+            }
+        }
+
+        let value = match value {
+            VarValue::Scalar(s) => self.attr_value(key, &s).map(VarValue::Scalar),
+            VarValue::Array(a)  => a.into_iter().map(|(k, v)| Some((k, self.attr_value(key, &v)?)))
+                                    .collect::<Option<_>>().map(VarValue::Array),
+            VarValue::Assoc(a)  => a.into_iter().map(|(k, v)| Some((k, self.attr_value(key, &v)?)))
+                                    .collect::<Option<_>>().map(VarValue::Assoc),
+        };
+        let value = match value {
+            Some(v) => v,
+            None    => return false,
+        };
+
+        if let VarValue::Scalar(s) = &value {
+            self.set_dynamic_var(key, s);
+        }
+        if key == "PATH" {
+            self.hash.table.clear();
+        }
+
+        let var = self.vars.entry(key, &Scope::Visible);
+        match (&var.value, value) {
+            (Some(VarValue::Array(_)), VarValue::Scalar(s)) 
+            | (Some(VarValue::Assoc(_)), VarValue::Scalar(s)) => {
+                let _ = var.set_element("0", s);
+            },
+            (_, v) => var.value = Some(v),
+        }
+        true
+    }
+
+    pub fn set_element(&mut self, key: &str, index: &str, value: &str) -> bool {
+        let (key, _) = self.resolve_nameref(key);
+        if key.is_empty() {
+            return false;
+        }
+        let index = self.subscript(&key, index);
+        let value = match self.attr_value(&key, value) {
+            Some(v) => v,
+            None    => return false,
+        };
+
+        let var = self.vars.entry(&key, &Scope::Visible);
+        if var.has_attr('r') {
+            eprintln!("bash: {}: readonly variable", key);
+            return false;
+        }
+
+        if let Err(e) = var.set_element(&index, value) {
+            eprintln!("bash: {}[{}", key, e);
+            return false;
+        }
+        true
+    }
+
+    /* elements of "name=(...)": (subscript, value) */
+    pub fn set_array(&mut self, key: &str, elems: Vec<(Option<String>, String)>) -> bool {
+        let (key, _) = self.resolve_nameref(key);
+        let key = key.as_str();
+        let assoc = match self.vars.get(key) {
+            Some(v) => v.has_attr('A'),
+            None    => false,
+        };
+
+        let value = if assoc {
+            let mut a = BTreeMap::new();
+            for (k, v) in elems {
+                match k {
+                    Some(k) => { a.insert(k, v); },
+                    None    => {
+                        eprintln!("bash: {}: {}: must use subscript when assigning associative array", key, v);
+                    },
+                }
+            }
+            VarValue::Assoc(a)
+        }else{
+            let mut a = BTreeMap::new();
+            let mut n = 0;
+            for (k, v) in elems {
+                if let Some(k) = k {
+                    match self.subscript(key, &k).parse::<usize>() {
+                        Ok(i) => n = i,
+                        _     => {
+                            eprintln!("bash: {}[{}]: bad array subscript", key, k);
+                            continue;
+                        },
+                    }
+                }
+                a.insert(n, v);
+                n += 1;
+            }
+            VarValue::Array(a)
+        };
+
+        if let Some(v) = self.vars.get_mut(key) {
+            if v.has_attr('r') {
+                eprintln!("bash: {}: readonly variable", key);
+                return false;
+            }
+            v.value = None;
+        }
+        self.set_value(key, value)
+    }
+
+    /* subscripts of indexed arrays are arithmetic expressions */
+    fn subscript(&mut self, key: &str, index: &str) -> String {
+        let assoc = match self.vars.get(key) {
+            Some(v) => v.has_attr('A'),
+            None    => false,
+        };
+
+        let index = index.trim();
+        if assoc || index.parse::<i64>().is_ok() {
+            return index.to_string();
+        }
+        calculate(index.to_string(), self)
+    }
+
+    pub fn get_element(&mut self, key: &str, index: &str) -> Option<String> {
+        let (key, _) = self.resolve_nameref(key);
+        let index = self.subscript(&key, index);
+        self.vars.get(&key)?.element(&index)
+    }
+
+    pub fn get_elements(&self, key: &str) -> Vec<String> {
+        let (key, index) = self.resolve_nameref(key);
+        if let Some(i) = index {
+            return self.vars.get(&key).and_then(|v| v.element(&i)).into_iter().collect();
+        }
+
+        match self.vars.get(&key) {
+            Some(v) => v.elements(),
+            None    => vec![],
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.vars.push_frame();
+    }
+
+    /* "NAME=value command": the variables are exported only while a builtin or a function runs */
+    pub fn push_temp_scope(&mut self, subs: &Vec<Assignment>) -> bool {
+        self.vars.push_temp_frame();
+        for a in subs {
+            if let Some(v) = self.vars.get(&a.name) {
+                if v.has_attr('r') {
+                    eprintln!("bash: {}: readonly variable", a.name);
+                    return false;
+                }
+            }
+            self.vars.entry(&a.name, &Scope::Temp).set_attr('x', true);
+            if ! a.apply(self) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn pop_scope(&mut self) {
+        if let Some(flags) = self.vars.pop_frame() {
+            self.flags = flags;
+        }
     }
 
     pub fn get_var(&self, key: &str) -> String {
@@ -105,25 +425,189 @@ impl ShellCore {
             return self.args[1..].to_vec().join(" ");
         }
 
-        if let Some(s) = self.vars.get(&key as &str){
-            return s.to_string();
+        let (key, index) = self.resolve_nameref(key);
+        if let Some(v) = self.vars.get(&key) {
+            if let (Some(ans), None) = (self.get_dynamic_var(&key), &index) {
+                return ans;
+            }
+            return match index {
+                Some(i) => v.element(&i).unwrap_or_default(),
+                None    => v.scalar().unwrap_or_default(),
+            };
         };
 
-        if let Ok(s) = env::var(&key) {
-            return s.to_string();
+        "".to_string()
+    }
+
+    fn get_dynamic_var(&self, key: &str) -> Option<String> {
+        if ! DYNAMIC_VARS.contains(&key) {
+            return None;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let ans = match key {
+            "RANDOM"        => self.random().to_string(),
+            "SRANDOM"       => {
+                let mut buf = [0u8; 4];
+                if let Ok(mut f) = File::open("/dev/urandom") {
+                    let _ = f.read_exact(&mut buf);
+                }
+                u32::from_ne_bytes(buf).to_string()
+            },
+            "SECONDS"       => self.seconds_base.elapsed().as_secs().to_string(),
+            "EPOCHSECONDS"  => now.as_secs().to_string(),
+            "EPOCHREALTIME" => format!("{}.{:06}", now.as_secs(), now.subsec_micros()),
+            "BASHPID"       => process::id().to_string(),
+            "BASH_SUBSHELL" => self.subshell_level.to_string(),
+            "HISTCMD"       => (self.history.len() + 1).to_string(),
+            "LINENO"        => self.lineno.to_string(),
+            _               => return None,
         };
+        Some(ans)
+    }
 
-        "".to_string()
+    fn set_dynamic_var(&mut self, key: &str, value: &str) {
+        if self.vars.get(key).is_none() {
+            return;
+        }
+
+        match key {
+            "RANDOM"  => self.random_seed.set(value.trim().parse::<i64>().unwrap_or(0) as u32),
+            "SECONDS" => {
+                let secs = value.trim().parse::<u64>().unwrap_or(0);
+                self.seconds_base = Instant::now().checked_sub(Duration::from_secs(secs))
+                                    .unwrap_or(Instant::now());
+            },
+            "BASH_SUBSHELL" => self.subshell_level = value.trim().parse::<usize>().unwrap_or(0),
+            _ => {},
+        }
+    }
+
+    /* the same generator as bash for the same sequence from the same seed */
+    fn random(&self) -> u32 {
+        let mut seed = self.random_seed.get() as i64 % 0x7fffffff;
+        if seed == 0 {
+            seed = 123459876;
+        }
+        let (h, l) = (seed / 127773, seed % 127773);
+        let mut t = 16807 * l - 2836 * h;
+        if t < 0 {
+            t += 0x7fffffff;
+        }
+        self.random_seed.set(t as u32);
+        (((t >> 16) ^ (t & 0xffff)) & 32767) as u32
+    }
+
+    pub fn is_set(&self, key: &str) -> bool {
+        if let Ok(n) = key.parse::<usize>() {
+            return self.args.len() > n;
+        }
+
+        let (key, index) = self.resolve_nameref(key);
+        if let Some(v) = self.vars.get(&key) {
+            return match index {
+                Some(i) => v.element(&i).is_some(),
+                None    => v.value.is_some(),
+            };
+        }
+
+        "-#@*".contains(&key)
+    }
+
+    /* BASH_FUNC_name%%=() { ... } in the environment, compatible with bash */
+    fn import_function(&mut self, name: &str, def: &str) {
+        let mut feeder = Feeder::new_from(format!("{} {}", name, def));
+        match FunctionDefinition::parse(&mut feeder, self) {
+            Some(mut f) if def.starts_with("() ") && f.name == name && feeder.len() == 0 => {
+                f.exec_elems(self);
+                self.exported_functions.insert(name.to_string());
+            },
+            _ => eprintln!("bash: error importing function definition for `{}'", name),
+        }
     }
 
-    pub fn get_function(&mut self, name: &String) -> Option<String> {
-        if self.functions.contains_key(name) {
-            if let Some(s) = self.functions.get(name) {
-                return Some(s.clone());
+    pub fn function_envs(&self) -> Vec<(String, String)> {
+        let mut ans = vec![];
+        for name in &self.exported_functions {
+            if let Some(f) = self.get_function(name) {
+                let def = format!("() {}", f.body.get_text().trim_end());
+                ans.push( (format!("BASH_FUNC_{}%%", name), def) );
             }
         }
+        ans.sort();
+        ans
+    }
+
+    /* the address of a local variable tells how deep the stack of this process is */
+    pub fn stack_exhausted(&self) -> bool {
+        let here = 0u8;
+        self.stack_base.saturating_sub(&here as *const u8 as usize) > self.stack_limit
+    }
+
+    pub fn function_level(&self) -> usize {
+        self.call_stack.iter().filter(|f| f.name != "source" && f.name != "main").count()
+    }
+
+    /* FUNCNEST or the limit of the stack */
+    pub fn check_nest(&mut self, name: &str, kind: &str, level: usize) -> bool {
+        let max = match kind {
+            "function" => self.get_var("FUNCNEST").parse::<usize>().unwrap_or(0),
+            _          => 0,
+        };
+        if (max > 0 && level >= max) || self.stack_exhausted() {
+            eprintln!("bash: {}: maximum {} nesting level exceeded ({})", name, kind, level);
+            self.set_var("?", "1");
+            self.discard_flag = true;
+            return false;
+        }
+        true
+    }
 
-        None
+    pub fn current_source(&self) -> String {
+        self.call_stack.last().map(|f| f.source.clone()).unwrap_or_default()
+    }
+
+    pub fn push_call(&mut self, name: &str, source: &str, args: &[String]) {
+        let frame = CallFrame::new(name, source, self.lineno, args);
+        self.call_stack.push(frame);
+        self.set_call_vars();
+    }
+
+    pub fn pop_call(&mut self) {
+        if self.call_stack.len() > 1 {
+            if let Some(f) = self.call_stack.pop() {
+                self.lineno = f.lineno;
+            }
+        }
+        self.set_call_vars();
+    }
+
+    /* FUNCNAME, BASH_SOURCE, BASH_LINENO and, with extdebug, BASH_ARGC and BASH_ARGV */
+    pub fn set_call_vars(&mut self) {
+        let frames: Vec<CallFrame> = self.call_stack.iter().rev().cloned().collect();
+        let to_array = |v: Vec<String>| Some(VarValue::Array(v.into_iter().enumerate().collect()));
+
+        let in_function = frames.iter().any(|f| f.name != "source" && f.name != "main");
+        self.vars.entry("FUNCNAME", &Scope::Global).value = match in_function {
+            true  => to_array(frames.iter().map(|f| f.name.clone()).collect()),
+            false => None,
+        };
+        self.vars.entry("BASH_SOURCE", &Scope::Global).value
+            = to_array(frames.iter().map(|f| f.source.clone()).collect());
+        self.vars.entry("BASH_LINENO", &Scope::Global).value
+            = to_array(frames.iter().map(|f| f.lineno.to_string()).collect());
+
+        if ! self.shopts.get("extdebug") {
+            return;
+        }
+        self.vars.entry("BASH_ARGC", &Scope::Global).value
+            = to_array(frames.iter().map(|f| f.args.len().to_string()).collect());
+        self.vars.entry("BASH_ARGV", &Scope::Global).value
+            = to_array(frames.iter().flat_map(|f| f.args.iter().rev().cloned()).collect());
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<Rc<ShellFunction>> {
+        self.functions.get(name).cloned()
     }
 
     pub fn get_builtin(&self, name: &String) 
@@ -142,6 +626,86 @@ impl ShellCore {
         false
     }
 
+    pub fn set_flag(&mut self, flag: char, on: bool) {
+        if on && ! self.has_flag(flag) {
+            self.flags.push(flag);
+        }else if ! on {
+            self.flags = self.flags.replace(flag, "");
+        }
+    }
+
+    /* expands a string as if it is double quoted */
+    pub fn expand_str(&mut self, s: &str) -> String {
+        let mut feeder = Feeder::new_from(format!("\"{}\"", s.replace("\"", "\\\"")));
+        match Word::parse(&mut feeder, self, false) {
+            Some(mut w) => Word::remove_escape(&w.eval(self).join(" ")),
+            None => s.to_string(),
+        }
+    }
+
+    fn expand_ps4(&mut self) -> String {
+        let ps4 = self.get_var("PS4");
+        let flags = self.flags.clone();
+        self.set_flag('x', false); // not to trace the expansion of PS4 itself
+        let ans = self.expand_str(&ps4);
+        self.flags = flags;
+        ans
+    }
+
+    pub fn xtrace(&mut self, text: &str) {
+        if ! self.has_flag('x') {
+            return;
+        }
+
+        let ps4 = self.expand_ps4();
+        let depth = match ps4.chars().next() {
+            Some(c) => c.to_string().repeat(self.comsub_level),
+            None    => "".to_string(),
+        };
+        let line = format!("{}{}{}\n", depth, ps4, text);
+
+        let fd = self.get_var("BASH_XTRACEFD").parse::<RawFd>().unwrap_or(2);
+        if write(fd, line.as_bytes()).is_err() {
+            eprint!("{}", line);
+        }
+    }
+
+    pub fn search_command(&mut self, com: &str) -> String {
+        let path = self.get_var("PATH");
+        let checkhash = self.shopts.get("checkhash");
+        self.hash.find(com, &path, checkhash)
+    }
+
+    /* a forked child such as ( ... ) or a command in a pipeline */
+    pub fn is_subshell(&self) -> bool {
+        self.get_var("$") != process::id().to_string()
+    }
+
+    /* called in a forked child that runs shell code; $$ is kept and BASHPID changes */
+    pub fn initialize_as_subshell(&mut self) {
+        self.subshell_level += 1;
+        self.initialize_as_child();
+    }
+
+    pub fn initialize_as_child(&mut self) { // a forked simple command is not a subshell in bash
+        self.loop_level = 0;
+        self.break_counter = 0;
+        proc::set_signals();
+    }
+
+    pub fn exit(&mut self, status: i32) -> ! {
+        if self.shopts.get("login_shell") && ! self.is_subshell() {
+            self.shopts.set(&"login_shell".to_string(), false); // "exit" in the file exits without reading it again
+            let home = self.get_var("HOME");
+            let file = home.clone() + "/.bash_logout";
+            if ! home.is_empty() && Path::new(&file).is_file() {
+                let f = self.builtins["source"];
+                f(self, &mut vec!["source".to_string(), file]);
+            }
+        }
+        process::exit(status);
+    }
+
     pub fn read_pipe(&mut self, pin: RawFd, pid: Pid) -> String {
         let mut ans = "".to_string();
         let mut ch = [0;1000];
@@ -189,7 +753,7 @@ impl ShellCore {
     }
 
     pub fn reverse_exit_status(&mut self) {
-        let rev = if self.vars["?"] == "0" {"1"}else{"0"};
+        let rev = if self.get_var("?") == "0" {"1"}else{"0"};
         self.set_var("?", rev);
     }
 
@@ -213,6 +777,43 @@ impl ShellCore {
             }
         }
 
+        self.close_finished_coprocs(false);
         self.jobs.remove_finished_jobs();
     }
+
+    /* checked before each pipeline so that a finished coprocess is noticed without wait */
+    pub fn reap_coprocs(&mut self) {
+        for j in self.jobs.backgrounds.iter_mut().filter(|j| j.coproc.is_some()) {
+            j.check_of_finish();
+        }
+        self.close_finished_coprocs(true);
+    }
+
+    /* bash closes the fds and removes NAME and NAME_PID after a coprocess ends.
+       With keep_unread, they are kept until the output of the coprocess is read up. */
+    pub fn close_finished_coprocs(&mut self, keep_unread: bool) {
+        for j in self.jobs.backgrounds.iter_mut() {
+            if j.status != 'D' && j.status != 'I' {
+                continue;
+            }
+            if let Some((_, fds)) = &j.coproc {
+                if keep_unread && Self::has_unread_data(fds[0]) {
+                    continue;
+                }
+            }
+            if let Some((name, fds)) = j.coproc.take() {
+                fds.iter().for_each(|fd| { let _ = close(*fd); });
+                self.vars.remove(&name);
+                self.vars.remove(&(name + "_PID"));
+            }
+        }
+    }
+
+    fn has_unread_data(fd: RawFd) -> bool {
+        let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+        match poll(&mut fds, 0) {
+            Ok(n) if n > 0 => fds[0].revents().is_some_and(|r| r.contains(PollFlags::POLLIN)),
+            _ => false,
+        }
+    }
 }
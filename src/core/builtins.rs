@@ -1,11 +1,14 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use std::process;
 use std::{io,fs,env};
 use std::fs::OpenOptions;
 use std::io::{Write, BufReader, BufRead};
-use crate::bash_glob::glob_match;
+use crate::bash_glob::{glob_match, GlobOptions};
+use crate::core::proc;
+use crate::utils::format_times;
+use nix::sys::resource::UsageWho;
+use std::time::Duration;
 use super::job::Job;
 use nix::sys::signal;
 use nix::sys::signal::Signal;
@@ -14,28 +17,39 @@ use std::path::Path;
 
 use crate::Script;
 use crate::ShellCore;
+use crate::core::CallFrame;
 use crate::Feeder;
+use crate::core::variables::{Scope, VarValue};
+use crate::elements::substitution::{Assignment, SubstValue};
 
 pub fn set_builtins(core: &mut ShellCore){
     core.builtins.insert(".".to_string(), source);
     core.builtins.insert(":".to_string(), true_);
     core.builtins.insert("alias".to_string(), alias);
     core.builtins.insert("builtin".to_string(), builtin);
+    core.builtins.insert("caller".to_string(), caller);
     core.builtins.insert("bg".to_string(), bg);
     core.builtins.insert("cd".to_string(), cd);
+    core.builtins.insert("declare".to_string(), declare);
     core.builtins.insert("eval".to_string(), eval);
     core.builtins.insert("exit".to_string(), exit);
     core.builtins.insert("export".to_string(), export);
     core.builtins.insert("false".to_string(), false_);
     core.builtins.insert("fg".to_string(), fg);
+    core.builtins.insert("hash".to_string(), hash);
     core.builtins.insert("history".to_string(), history);
     core.builtins.insert("jobs".to_string(), jobs);
+    core.builtins.insert("local".to_string(), local);
     core.builtins.insert("pwd".to_string(), pwd);
     core.builtins.insert("set".to_string(), set);
     core.builtins.insert("shift".to_string(), shift);
     core.builtins.insert("true".to_string(), true_);
+    core.builtins.insert("times".to_string(), times);
+    core.builtins.insert("typeset".to_string(), typeset);
     core.builtins.insert("read".to_string(), read);
+    core.builtins.insert("readonly".to_string(), readonly);
     core.builtins.insert("return".to_string(), return_);
+    core.builtins.insert("break".to_string(), break_);
     core.builtins.insert("shopt".to_string(), shopt);
     core.builtins.insert("source".to_string(), source);
     core.builtins.insert("unset".to_string(), unset);
@@ -45,9 +59,9 @@ pub fn set_builtins(core: &mut ShellCore){
 }
 
 pub fn exit(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let home = env::var("HOME").expect("HOME is not defined");
-    if let Ok(mut hist_file) = OpenOptions::new().write(true)
-                               .append(true).open(home + "/.bash_history") {
+    let home = core.get_var("HOME");
+    let hist = OpenOptions::new().append(true).open(home + "/.bash_history");
+    if let (false, Ok(mut hist_file)) = (core.is_subshell(), hist) {
         for h in &core.history {
             write!(hist_file, "{}\n", h).expect("Cannot write history");
         };
@@ -56,23 +70,134 @@ pub fn exit(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
 
     if args.len() >= 2 {
         if let Ok(status) = args[1].parse::<i32>(){
-            process::exit(status);
+            core.exit(status & 255);
         }else{
-            eprintln!("exit: {}: numeric wordument required", args[1]);
-            process::exit(2);
+            eprintln!("bash: exit: {}: numeric argument required", args[1]);
+            core.exit(2);
         }
     }
 
     if let Ok(status) = core.get_var("?").to_string().parse::<i32>(){
-        process::exit(status);
+        core.exit(status);
     }else{
         eprintln!("Shell internal error");
-        process::exit(1);
+        core.exit(1);
     }
 }
 
-pub fn history(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
-    let home = env::var("HOME").expect("HOME is not defined");
+fn hash_usage(msg: &str) -> i32 {
+    eprintln!("bash: hash: {}", msg);
+    eprintln!("hash: usage: hash [-lr] [-p pathname] [-dt] [name ...]");
+    2
+}
+
+fn print_hash(core: &ShellCore, list_form: bool) -> i32 {
+    if core.hash.table.is_empty() {
+        println!("hash: hash table empty");
+        return 0;
+    }
+
+    if ! list_form {
+        println!("hits\tcommand");
+    }
+    for (name, (path, hits)) in &core.hash.table {
+        match list_form {
+            true  => println!("builtin hash -p {} {}", path, name),
+            false => println!("{:4}\t{}", hits, path),
+        }
+    }
+    0
+}
+
+pub fn hash(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (mut list_form, mut delete, mut show_path, mut reset) = (false, false, false, false);
+    let mut path = None;
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        if args[pos] == "--" {
+            pos += 1;
+            break;
+        }
+        for (i, ch) in args[pos].chars().enumerate().skip(1) {
+            match ch {
+                'r' => reset = true,
+                'l' => list_form = true,
+                'd' => delete = true,
+                't' => show_path = true,
+                'p' => {
+                    let rest = args[pos][i+1..].to_string();
+                    if ! rest.is_empty() {
+                        path = Some(rest);
+                    }else if pos + 1 < args.len() {
+                        pos += 1;
+                        path = Some(args[pos].clone());
+                    }else{
+                        return hash_usage("-p: option requires an argument");
+                    }
+                    break;
+                },
+                _ => return hash_usage(&format!("-{}: invalid option", ch)),
+            }
+        }
+        pos += 1;
+    }
+
+    core.hash.sync(&core.get_var("PATH"));
+    if reset {
+        core.hash.table.clear();
+    }
+    let names = args[pos..].to_vec();
+    if show_path && names.is_empty() {
+        eprintln!("bash: hash: -t: option requires an argument");
+        return 1;
+    }
+    if names.is_empty() && reset && path.is_none() && ! list_form {
+        return 0;
+    }
+    if names.is_empty() {
+        return print_hash(core, list_form);
+    }
+
+    let mut status = 0;
+    for name in &names {
+        if let Some(p) = &path {
+            core.hash.table.insert(name.to_string(), (p.to_string(), 0));
+            continue;
+        }
+
+        let found = if delete {
+            core.hash.table.remove(name).is_some()
+        }else if show_path {
+            match core.hash.table.get_mut(name) {
+                Some((p, hits)) => {
+                    *hits += 1;
+                    match (list_form, names.len()) {
+                        (true, _) => println!("builtin hash -p {} {}", p, name),
+                        (_, 1)    => println!("{}", p),
+                        _         => println!("{}\t{}", name, p),
+                    }
+                    true
+                },
+                None => false,
+            }
+        }else if name.contains('/') || core.get_builtin(name).is_some()
+                 || core.functions.contains_key(name) {
+            true
+        }else{
+            let path = core.get_var("PATH");
+            core.hash.register(name, &path)
+        };
+
+        if ! found {
+            eprintln!("bash: hash: {}: not found", name);
+            status = 1;
+        }
+    }
+    status
+}
+
+pub fn history(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
+    let home = core.get_var("HOME");
     if let Ok(hist_file) = OpenOptions::new().read(true).open(home + "/.bash_history") {
         let reader = BufReader::new(hist_file);
         for (i, line) in reader.lines().enumerate() {
@@ -98,6 +223,16 @@ pub fn true_(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
     0
 }
 
+pub fn times(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
+    for who in [UsageWho::RUSAGE_SELF, UsageWho::RUSAGE_CHILDREN] {
+        let (user, sys) = proc::cpu_times(who);
+        if let Ok(s) = format_times("%3lU %3lS", Duration::ZERO, user, sys) {
+            println!("{}", s);
+        }
+    }
+    0
+}
+
 pub fn false_(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
     1
 }
@@ -230,13 +365,15 @@ pub fn cd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     }
 
 
+    let back = args.len() == 2 && args[1] == "-";
     if args.len() == 1 { //only "cd"
-        let var = env::var("HOME").expect("HOME is not defined");
-        args.push(var);
-    }else if args.len() == 2 && args[1] == "-" { // cd -
-        if let Some(old) = core.vars.get("OLDPWD") {
-            args[1] = old.to_string();
+        args.push(core.get_var("HOME"));
+    }else if back { // cd -
+        if ! core.is_set("OLDPWD") {
+            eprintln!("bash: cd: OLDPWD not set");
+            return 1;
         }
+        args[1] = core.get_var("OLDPWD");
     };
 
     if let Ok(old) = env::current_dir() {
@@ -248,6 +385,9 @@ pub fn cd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
         if let Ok(full) = fs::canonicalize(path) {
             core.set_var("PWD", &full.display().to_string());
         }
+        if back {
+            println!("{}", core.get_var("PWD"));
+        }
         0
     }else{
         eprintln!("Not exist directory");
@@ -278,39 +418,401 @@ pub fn alias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     0
 }
 
+pub fn option_to_flag(name: &str) -> Option<char> {
+    match name {
+        "allexport"   => Some('a'),
+        "braceexpand" => Some('B'),
+        "errexit"     => Some('e'),
+        "hashall"     => Some('h'),
+        "monitor"     => Some('m'),
+        "noclobber"   => Some('C'),
+        "noexec"      => Some('n'),
+        "noglob"      => Some('f'),
+        "nounset"     => Some('u'),
+        "verbose"     => Some('v'),
+        "xtrace"      => Some('x'),
+        _             => None,
+    }
+}
+
 pub fn set(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if args.len() == 1 {
-       for k in core.vars.keys() {
-           println!("{}={}", k, core.vars[k]);
+       for k in core.vars.names() {
+           if let Some(v) = core.vars.get(&k).and_then(|v| v.value_text(true)) {
+               println!("{}={}", k, v);
+           }
        }
        return 0;
     }
 
-    core.args.clear();
+    let mut pos = 1;
+    let mut positional = false;
+    while pos < args.len() {
+        let arg = args[pos].clone();
+        if arg == "--" {
+            positional = true;
+            pos += 1;
+            break;
+        }
+
+        let on = arg.starts_with("-");
+        if arg.len() < 2 || ! (on || arg.starts_with("+")) {
+            positional = true;
+            break;
+        }
 
-    for a in args {
-        core.args.push(a.to_string());
+        if &arg[1..] == "o" {
+            if pos+1 >= args.len() {
+                eprintln!("bash: set: {}o: option requires an argument", &arg[0..1]);
+                return 2;
+            }
+            match option_to_flag(&args[pos+1]) {
+                Some(f) => core.set_flag(f, on),
+                None    => {
+                    eprintln!("bash: set: {}: invalid option name", &args[pos+1]);
+                    return 2;
+                },
+            }
+            pos += 2;
+            continue;
+        }
+
+        for ch in arg[1..].chars() {
+            if ! "abdefhkmnptuvxBCEHPT".contains(ch) {
+                eprintln!("bash: set: {}{}: invalid option", &arg[0..1], ch);
+                return 2;
+            }
+            core.set_flag(ch, on);
+        }
+        pos += 1;
+    }
+
+    if positional {
+        core.args.truncate(1);
+        for a in &args[pos..] {
+            core.args.push(a.to_string());
+        }
     }
 
     0
 }
 
 pub fn unset(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 {
+    let mut mode = "";
+    let mut status = 0;
+    for arg in args[1..].iter() {
+        if arg == "-v" || arg == "-f" || arg == "-n" {
+            mode = arg;
+            continue;
+        }
+
+        if mode == "-f" {
+            core.functions.remove(arg);
+            core.exported_functions.remove(arg);
+            continue;
+        }
+
+        let (name, index) = match arg.split_once('[') {
+            Some((n, i)) if i.ends_with(']') => (n.to_string(), Some(i[..i.len()-1].to_string())),
+            _ if mode == "-n" => (arg.to_string(), None),
+            _ => core.resolve_nameref(arg),
+        };
+
+        match core.vars.get_mut(&name) {
+            Some(v) if v.has_attr('r') => {
+                eprintln!("bash: unset: {}: cannot unset: readonly variable", name);
+                status = 1;
+            },
+            Some(v) if index.is_some() => v.unset_element(&index.unwrap_or_default()),
+            Some(_) => { core.vars.remove(&name); },
+            None => {
+                if mode.is_empty() {
+                    core.functions.remove(&name);
+                    core.exported_functions.remove(&name);
+                }
+            },
+        }
+    }
+    status
+}
+
+fn is_name(s: &str) -> bool {
+    Feeder::new_from(s.to_string()).scanner_name() == s.len()
+}
+
+/* returns attributes to give, attributes to remove and the position of the first name */
+fn declare_options(com: &str, args: &[String]) -> Result<(String, String, usize), i32> {
+    let mut on = String::new();
+    let mut off = String::new();
+    let mut pos = 1;
+    while pos < args.len() {
+        let arg = &args[pos];
+        if arg == "--" {
+            pos += 1;
+            break;
+        }
+        if arg.len() < 2 || ! (arg.starts_with("-") || arg.starts_with("+")) {
+            break;
+        }
+
+        for ch in arg[1..].chars() {
+            if ! "aAfFgilnprtux".contains(ch) {
+                eprintln!("bash: {}: {}{}: invalid option", com, &arg[0..1], ch);
+                eprintln!("{}: usage: {} [-aAfFgilnrtux] [-p] [name[=value] ...]", com, com);
+                return Err(2);
+            }
+            if arg.starts_with("-") {
+                on.push(ch);
+            }else{
+                off.push(ch);
+            }
+        }
+        pos += 1;
+    }
+    Ok((on, off, pos))
+}
+
+fn declare_functions(core: &mut ShellCore, names: &[String], only_names: bool) -> i32 {
+    let mut names = names.to_vec();
+    let extdebug = ! names.is_empty() && core.shopts.get("extdebug");
+    if names.is_empty() {
+        names = core.functions.keys().cloned().collect();
+        names.sort();
+    }
+
+    let mut status = 0;
+    for name in names {
+        match core.get_function(&name) {
+            Some(f) if only_names && extdebug => println!("{} {} {}", name, f.lineno, f.source),
+            Some(_) if only_names => println!("declare -f {}", name),
+            Some(f) => println!("{}", f.text),
+            None    => status = 1,
+        }
+    }
+    status
+}
+
+fn declare_print(core: &mut ShellCore, com: &str, names: &[String], on: &str) -> i32 {
+    let filter = on.chars().filter(|c| "aAilnrtux".contains(*c)).collect::<String>();
+
+    if names.is_empty() {
+        for name in core.vars.names() {
+            let var = match core.vars.get(&name) {
+                Some(v) => v,
+                None    => continue,
+            };
+            let text = var.declare_text(&name);
+            let flags = text.split(' ').nth(1).unwrap_or("");
+            if ! filter.chars().all(|c| flags.contains(c)) {
+                continue;
+            }
+
+            match (on.len(), var.value_text(true)) {
+                (0, Some(v)) => println!("{}={}", name, v),
+                (0, None)    => {},
+                _            => println!("{}", text),
+            }
+        }
         return 0;
     }
 
-    for key in args[1..].iter() {
-        if ! core.vars.contains_key(key) {
-            core.vars.remove(key);
+    let mut status = 0;
+    for name in names {
+        match core.vars.get(name) {
+            Some(v) => println!("{}", v.declare_text(name)),
+            None    => {
+                eprintln!("bash: {}: {}: not found", com, name);
+                status = 1;
+            },
         }
     }
-    0
+    status
+}
+
+fn declare_vars(core: &mut ShellCore, com: &str, args: &[String]) -> i32 {
+    let (mut on, off, pos) = match declare_options(com, args) {
+        Ok(r)  => r,
+        Err(s) => return s,
+    };
+    if com == "readonly" {
+        on.push('r');
+    }
+    if com == "local" && ! core.vars.in_function() {
+        eprintln!("bash: local: can only be used in a function");
+        return 1;
+    }
+
+    if on.contains('f') || on.contains('F') {
+        return declare_functions(core, &args[pos..], on.contains('F'));
+    }
+    if pos >= args.len() || on.contains('p') {
+        return declare_print(core, com, &args[pos..], &on);
+    }
+
+    let scope = if on.contains('g') {
+        Scope::Global
+    }else if com == "local" || (com != "readonly" && core.vars.in_function()) {
+        Scope::Local
+    }else{
+        Scope::Visible
+    };
+
+    let mut status = 0;
+    for arg in &args[pos..] {
+        if com == "local" && arg == "-" {
+            let flags = core.flags.clone();
+            core.vars.save_flags(&flags);
+            continue;
+        }
+
+        let assign = match arg.contains('=') {
+            true  => Assignment::from_arg(arg, core),
+            false => None,
+        };
+        let name = match &assign {
+            Some(a) => a.name.clone(),
+            None    => arg.to_string(),
+        };
+        if ! is_name(&name) {
+            eprintln!("bash: {}: `{}': not a valid identifier", com, arg);
+            status = 1;
+            continue;
+        }
+
+        let shadowing_readonly = match (&scope, core.vars.get(&name)) {
+            (Scope::Local, Some(v)) => v.has_attr('r'),
+            _ => false,
+        };
+        if shadowing_readonly {
+            eprintln!("bash: {}: {}: readonly variable", com, name);
+            status = 1;
+            continue;
+        }
+
+        let var = core.vars.entry(&name, &scope);
+        if var.has_attr('r') && (assign.is_some() || off.contains('r')) {
+            eprintln!("bash: {}: {}: readonly variable", com, name);
+            status = 1;
+            continue;
+        }
+        for ch in on.chars().filter(|c| "aAilnux".contains(*c)) {
+            var.set_attr(ch, true);
+        }
+        for ch in off.chars().filter(|c| "ilnux".contains(*c)) {
+            var.set_attr(ch, false);
+        }
+
+        let ok = match assign {
+            Some(a) if on.contains('n') => set_nameref(core, com, &name, &a),
+            Some(a) => a.apply(core),
+            None => true,
+        };
+        if ! ok {
+            status = 1;
+        }
+
+        let var = core.vars.entry(&name, &scope);
+        if on.contains('r') {
+            var.set_attr('r', true);
+        }
+    }
+    status
+}
+
+/* the value of a nameref is the name of the referred variable */
+fn set_nameref(core: &mut ShellCore, com: &str, name: &str, assign: &Assignment) -> bool {
+    let target = match (&assign.index, &assign.value) {
+        (None, SubstValue::Scalar(v)) => v.clone(),
+        _ => {
+            eprintln!("bash: {}: {}: reference variable cannot be an array", com, name);
+            return false;
+        },
+    };
+
+    let target_name = target.split('[').next().unwrap_or("");
+    if ! is_name(target_name) {
+        eprintln!("bash: {}: `{}': invalid variable name for name reference", com, target);
+        return false;
+    }
+    if target_name == name {
+        eprintln!("bash: {}: {}: nameref variable self references not allowed", com, name);
+        return false;
+    }
+
+    match core.vars.get_mut(name) {
+        Some(v) => {
+            v.value = Some(VarValue::Scalar(target));
+            true
+        },
+        None => false,
+    }
+}
+
+pub fn declare(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    declare_vars(core, "declare", args)
+}
+
+pub fn typeset(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    declare_vars(core, "typeset", args)
+}
+
+pub fn local(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    declare_vars(core, "local", args)
+}
+
+pub fn readonly(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    declare_vars(core, "readonly", args)
+}
+
+/* reads a byte at a time not to take the following lines from the fd */
+fn read_line_from_fd(fd: i32, line: &mut String) -> Result<usize, String> {
+    let mut bytes = vec![];
+    let mut ch = [0; 1];
+    loop {
+        match unistd::read(fd, &mut ch) {
+            Ok(0) => break,
+            Ok(_) => {
+                bytes.push(ch[0]);
+                if ch[0] == b'\n' {
+                    break;
+                }
+            },
+            Err(_) => return Err(format!("bash: read: {}: invalid file descriptor: Bad file descriptor", fd)),
+        }
+    }
+    *line = String::from_utf8_lossy(&bytes).to_string();
+    Ok(bytes.len())
 }
 
 pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut fd = None;
+    if args.len() > 1 && args[1] == "-u" {
+        match args.get(2).map(|a| a.parse::<i32>()) {
+            Some(Ok(n)) if n >= 0 => fd = Some(n),
+            Some(_) => {
+                eprintln!("bash: read: {}: invalid file descriptor specification", args[2]);
+                return 1;
+            },
+            None => {
+                eprintln!("bash: read: -u: option requires an argument");
+                return 2;
+            },
+        }
+        args.drain(1..3);
+    }
+
     let mut line = String::new();
-    if io::stdin().read_line(&mut line).expect("Failed to read line") == 0 {
+    let len = match fd {
+        Some(n) => match read_line_from_fd(n, &mut line) {
+            Ok(len) => len,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            },
+        },
+        None => io::stdin().read_line(&mut line).expect("Failed to read line"),
+    };
+    if len == 0 {
         return 1;
     }
 
@@ -348,38 +850,152 @@ pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     0
 }
 
+/* for source and eval: parsing and execution line by line until an error or return */
+fn exec_lines(core: &mut ShellCore, feeder: &mut Feeder) {
+    core.syntax_error = false;
+    while ! core.discard_flag && ! core.syntax_error && ! core.return_flag && feeder.feed_line(core) {
+        while let Some(mut script) = Script::parse(feeder, core) {
+            script.exec(core);
+            if core.discard_flag || core.return_flag {
+                break;
+            }
+        }
+    }
+}
+
+fn find_source_file(core: &mut ShellCore, name: &str) -> String {
+    if name.contains('/') || ! core.shopts.get("sourcepath") {
+        return name.to_string();
+    }
+
+    for dir in core.get_var("PATH").split(':') {
+        let path = format!("{}/{}", dir, name);
+        if Path::new(&path).is_file() {
+            return path;
+        }
+    }
+    name.to_string()
+}
+
 pub fn source(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if args.len() < 2 {
-        eprintln!("usage: source filename");
+        eprintln!("bash: source: filename argument required");
+        eprintln!("source: usage: source filename [arguments]");
+        return 2;
+    }
+
+    let file = find_source_file(core, &args[1]);
+    let source = match fs::read_to_string(&file) {
+        Ok(s) => s,
+        Err(_) => {
+            eprintln!("bash: {}: No such file or directory", &args[1]);
+            return 1;
+        },
+    };
+
+    let level = core.call_stack.len() - 1;
+    if ! core.check_nest("source", "source", level) {
+        return 1;
+    }
+
+    let backup = core.args.clone();
+    if args.len() > 2 {
+        core.args = vec![backup[0].clone()];
+        core.args.extend(args[2..].iter().cloned());
+    }
+
+    let mut feeder = Feeder::new_lines(&source, &file, 1);
+    core.set_var("?", "0");
+    core.push_call("source", &file, &args[2..]);
+    exec_lines(core, &mut feeder);
+    core.return_flag = false;
+    core.pop_call();
+
+    if args.len() > 2 {
+        core.args = backup;
+    }
+
+    if core.discard_flag {
+        return 1;
+    }
+    if core.syntax_error {
+        core.syntax_error = false;
+        return 2;
+    }
+    core.get_var("?").parse::<i32>().unwrap_or(1)
+}
+
+pub fn caller(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let frames: Vec<&CallFrame> = core.call_stack.iter().rev().collect();
+
+    if args.len() < 2 {
+        let source = match frames.get(1) {
+            Some(f) => f.source.clone(),
+            None    => "NULL".to_string(),
+        };
+        println!("{} {}", frames[0].lineno, source);
+        return 0;
+    }
+
+    let n = match args[1].parse::<usize>() {
+        Ok(n) => n,
+        Err(_) => {
+            eprintln!("bash: caller: {}: invalid number", args[1]);
+            eprintln!("caller: usage: caller [expr]");
+            return 2;
+        },
+    };
+
+    match (frames.get(n), frames.get(n+1)) {
+        (Some(f), Some(up)) => {
+            println!("{} {} {}", f.lineno, up.name, up.source);
+            0
+        },
+        _ => 1,
+    }
+}
+
+pub fn return_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if core.call_stack.len() < 2 {
+        eprintln!("bash: return: can only `return' from a function or sourced script");
         return 1;
     }
 
-    if args.len() > 1 {
-        match fs::read_to_string(&args[1]) {
-            Ok(source) => {
-                let mut feeder = Feeder::new_from(source);
-                if let Some(mut script) = Script::parse(&mut feeder, core) {
-                    core.return_enable = true;
-                    script.exec(core);
-                    core.return_enable = false;
-                }else{
-                    return 1;
-                };
+    core.return_flag = true;
+    match args.get(1) {
+        None    => core.get_var("?").parse::<i32>().unwrap_or(0),
+        Some(a) => match a.parse::<i32>() {
+            Ok(n)  => n & 255,
+            Err(_) => {
+                eprintln!("bash: return: {}: numeric argument required", a);
+                2
             },
-            _ => eprintln!("Cannot read the source file: {}", &args[1]),
-        }
+        },
     }
-    0
 }
 
-pub fn return_(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
-    if core.return_enable {
-        core.return_flag = true;
-        0
-    }else{
-        eprintln!("Builtin return is only enabled in a function or source");
-        1
+pub fn break_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if core.loop_level == 0 {
+        eprintln!("bash: break: only meaningful in a `for', `while', or `until' loop");
+        return 0;
     }
+
+    let (n, status) = match args.get(1).map(|a| a.parse::<i64>()) {
+        None => (1, 0),
+        Some(Ok(n)) if n > 0 => (n as u32, 0),
+        Some(Ok(n)) => {
+            eprintln!("bash: break: {}: loop count out of range", n);
+            (1, 1)
+        },
+        Some(Err(_)) => {
+            eprintln!("bash: break: {}: numeric argument required", args[1]);
+            core.discard_flag = true;
+            (1, 1)
+        },
+    };
+
+    core.break_counter = std::cmp::min(n, core.loop_level);
+    status
 }
 
 pub fn jobs(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
@@ -430,31 +1046,96 @@ pub fn shopt(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     0
 }
 
-pub fn export(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 { // TODO: it should output all env vars. 
-        return 1;
+fn export_functions(core: &mut ShellCore, names: &[String], on: bool) -> i32 {
+    if names.is_empty() {
+        let mut names = core.exported_functions.iter().cloned().collect::<Vec<String>>();
+        names.sort();
+        for name in names {
+            if let Some(f) = core.get_function(&name) {
+                println!("{}", f.text);
+                println!("declare -fx {}", name);
+            }
+        }
+        return 0;
     }
 
-    let key_value = args[1].split("=").map(|s| s.to_string()).collect::<Vec<String>>();
+    let mut status = 0;
+    for name in names {
+        if ! core.functions.contains_key(name) {
+            eprintln!("bash: export: {}: not a function", name);
+            status = 1;
+        }else if on {
+            core.exported_functions.insert(name.to_string());
+        }else{
+            core.exported_functions.remove(name);
+        }
+    }
+    status
+}
 
-    let (key, value) = if key_value.len() == 0 {
-        return 1;
-    }else if key_value.len() == 1 {
-        (&key_value[0], "".to_string())
-    }else{
-        (&key_value[0], key_value[1..].join("="))
-    };
+pub fn export(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut opts = String::new();
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        if args[pos] == "--" {
+            pos += 1;
+            break;
+        }
+        for ch in args[pos][1..].chars() {
+            if ! "fnp".contains(ch) {
+                eprintln!("bash: export: -{}: invalid option", ch);
+                eprintln!("export: usage: export [-fn] [name[=value] ...] or export -p");
+                return 2;
+            }
+            opts.push(ch);
+        }
+        pos += 1;
+    }
 
-    if ! core.vars.contains_key(key) {
-        env::set_var(key, value);
+    if opts.contains('f') {
+        return export_functions(core, &args[pos..], ! opts.contains('n'));
+    }
+
+    if pos >= args.len() {
+        for name in core.vars.names() {
+            match core.vars.get(&name) {
+                Some(v) if v.has_attr('x') => println!("{}", v.declare_text(&name)),
+                _ => {},
+            }
+        }
         return 0;
     }
 
-    let value = core.get_var(&key);
-    env::set_var(key, value);
-    core.vars.remove(key);
+    let mut status = 0;
+    for arg in &args[pos..] {
+        let assign = match arg.contains('=') {
+            true  => Assignment::from_arg(arg, core),
+            false => None,
+        };
+        let name = match &assign {
+            Some(a) => a.name.clone(),
+            None    => arg.to_string(),
+        };
+        if ! is_name(&name) {
+            eprintln!("bash: export: `{}': not a valid identifier", arg);
+            status = 1;
+            continue;
+        }
 
-    0
+        if let Some(a) = assign {
+            if ! a.apply(core) {
+                status = 1;
+                continue;
+            }
+        }
+        let (name, _) = core.resolve_nameref(&name);
+        if name.is_empty() {
+            status = 1;
+            continue;
+        }
+        core.vars.entry(&name, &Scope::Visible).set_attr('x', ! opts.contains('n'));
+    }
+    status
 }
 
 pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
@@ -462,18 +1143,38 @@ pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
         return 0;
     }
 
-    let text = args[1..].join(" ");
-    let mut feeder = Feeder::new_from(text);
-        //eprintln!("{:?}", feeder._text());
-    if let Some(mut script) = Script::parse(&mut feeder, core) {
-        script.exec(core);
+    let level = core.eval_level;
+    if ! core.check_nest("eval", "eval", level) {
+        return 1;
     }
 
-    core.get_var("?").parse::<i32>().unwrap()
+    let text = args[1..].join(" ");
+    let lineno = core.lineno;
+    let mut feeder = Feeder::new_lines(&text, "bash: eval", lineno);
+    core.eval_level += 1;
+    core.set_var("?", "0");
+    exec_lines(core, &mut feeder);
+    core.eval_level -= 1;
+    core.lineno = lineno;
+
+    if core.discard_flag {
+        core.discard_flag = false;
+        return 1;
+    }
+    if core.syntax_error {
+        core.syntax_error = false;
+        return 2;
+    }
+    core.get_var("?").parse::<i32>().unwrap_or(1)
 }
 
-pub fn glob_test(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if glob_match(&args[1].to_string(), &args[2].to_string()){
+pub fn glob_test(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let opts = GlobOptions {
+        nocase: core.shopts.get("nocasematch"),
+        ..GlobOptions::new(&core.shopts)
+    };
+
+    if glob_match(&args[1].to_string(), &args[2].to_string(), &opts){
         eprintln!("MATCH!");
         0
     }else{
@@ -496,6 +1197,7 @@ pub fn wait(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
         eprintln!("{}", &core.jobs.backgrounds[i].status_string(first, second));
         core.jobs.backgrounds[i].status = 'I';
     }
+    core.close_finished_coprocs(false);
 
     0
 }
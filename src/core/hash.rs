@@ -0,0 +1,78 @@
+//SPDX-FileCopyrightText: 2023 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::utils::get_fullpath;
+use nix::unistd::{access, AccessFlags};
+
+/* remembered paths of external commands */
+#[derive(Debug, Clone)]
+pub struct CommandHash {
+    pub table: BTreeMap<String, (String, usize)>, // name -> (path, hits)
+    path: String, // $PATH when the entries were registered
+}
+
+impl CommandHash {
+    pub fn new() -> CommandHash {
+        CommandHash {
+            table: BTreeMap::new(),
+            path: String::new(),
+        }
+    }
+
+    /* all entries are forgotten when PATH is changed */
+    pub fn sync(&mut self, path: &str) {
+        if self.path != path {
+            self.table.clear();
+            self.path = path.to_string();
+        }
+    }
+
+    pub fn find(&mut self, com: &str, path: &str, checkhash: bool) -> String {
+        self.sync(path);
+        if let Some((p, hits)) = self.table.get_mut(com) {
+            if ! checkhash || Self::is_executable(p) {
+                *hits += 1;
+                return p.clone();
+            }
+        }
+
+        if com.contains('/') {
+            return get_fullpath(&com.to_string(), path);
+        }
+
+        match Self::search_path(com, path) {
+            Some(p) => {
+                self.table.insert(com.to_string(), (p.clone(), 1));
+                p
+            },
+            None => {
+                self.table.remove(com);
+                get_fullpath(&com.to_string(), path)
+            },
+        }
+    }
+
+    /* registers the command without executing it like "hash name" */
+    pub fn register(&mut self, com: &str, path: &str) -> bool {
+        self.sync(path);
+        match Self::search_path(com, path) {
+            Some(p) => {
+                self.table.insert(com.to_string(), (p, 0));
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn search_path(com: &str, path: &str) -> Option<String> {
+        path.split(':').filter(|d| ! d.is_empty())
+            .map(|d| format!("{}/{}", d, com))
+            .find(|p| Self::is_executable(p))
+    }
+
+    fn is_executable(path: &str) -> bool {
+        Path::new(path).is_file() && access(path, AccessFlags::X_OK).is_ok()
+    }
+}
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use nix::unistd::Pid;
+use std::os::unix::prelude::RawFd;
 use crate::elements::command::Command;
 use super::proc;
 
@@ -15,6 +16,7 @@ pub struct Job {
     pub status: char, // S: stopped, R: running, D: done, I: invalid, F: fg
     pub id: usize,
     pub priority: u32,
+    pub coproc: Option<(String, Vec<RawFd>)>, // name and fds of a coprocess
 }
 
 impl Job {
@@ -34,6 +36,7 @@ impl Job {
             status: if is_bg {'R'}else{'F'},
             id: 0,
             priority: 0, 
+            coproc: None,
         }
     }
 
@@ -7,6 +7,18 @@ use std::fs;
 
 use nix::sys::signal;
 use nix::sys::signal::{Signal, SigHandler};
+use nix::sys::resource::{getrusage, UsageWho};
+use nix::sys::time::TimeVal;
+use std::time::Duration;
+
+/* user and system times of the shell or of the waited children */
+pub fn cpu_times(who: UsageWho) -> (Duration, Duration) {
+    let to_duration = |t: TimeVal| Duration::new(t.tv_sec() as u64, t.tv_usec() as u32 * 1000);
+    match getrusage(who) {
+        Ok(u) => (to_duration(u.user_time()), to_duration(u.system_time())),
+        Err(_) => (Duration::ZERO, Duration::ZERO),
+    }
+}
 
 pub fn wait_process(child: Pid) -> i32 {
     let exit_status = match waitpid(child, Some(WaitPidFlag::WUNTRACED)) {
@@ -6,10 +6,10 @@ use std::collections::HashMap;
 pub struct Shopts(HashMap<String, bool>);
 
 impl Shopts {
-    pub fn _get(self, key: &str) -> Option<bool> {
+    pub fn get(&self, key: &str) -> bool {
         match self.0.get(key) {
-            Some(ans) => return Some(*ans),
-            _ => return None,
+            Some(ans) => *ans,
+            _ => false,
         }
     }
 
@@ -102,7 +102,7 @@ impl Shopts {
         ans.insert("promptvars".to_string(), false); 
         ans.insert("restricted_shell".to_string(), false); 
         ans.insert("shift_verbose".to_string(), false); 
-        ans.insert("sourcepath".to_string(), false); 
+        ans.insert("sourcepath".to_string(), true); 
         ans.insert("xpg_echo".to_string(), false); 
 
         Shopts(ans)
@@ -0,0 +1,298 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use std::collections::{BTreeMap, HashMap};
+use crate::utils::{quote, dquote};
+
+#[derive(Debug, Clone)]
+pub enum VarValue {
+    Scalar(String),
+    Array(BTreeMap<usize, String>),
+    Assoc(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub value: Option<VarValue>, // None: declared but unset
+    pub attrs: String,
+}
+
+impl Variable {
+    pub fn new() -> Variable {
+        Variable { value: None, attrs: String::new() }
+    }
+
+    pub fn has_attr(&self, attr: char) -> bool {
+        self.attrs.contains(attr)
+    }
+
+    pub fn set_attr(&mut self, attr: char, on: bool) {
+        if on && ! self.has_attr(attr) {
+            self.attrs.push(attr);
+        }else if ! on {
+            self.attrs = self.attrs.replace(attr, "");
+        }
+
+        match (attr, on) {
+            ('l', true) => self.attrs = self.attrs.replace('u', ""),
+            ('u', true) => self.attrs = self.attrs.replace('l', ""),
+            _ => {},
+        }
+
+        match (attr, on, &self.value) {
+            ('a', true, None) => self.value = Some(VarValue::Array(BTreeMap::new())),
+            ('a', true, Some(VarValue::Scalar(s))) => {
+                let mut a = BTreeMap::new();
+                a.insert(0, s.clone());
+                self.value = Some(VarValue::Array(a));
+            },
+            ('A', true, None) => self.value = Some(VarValue::Assoc(BTreeMap::new())),
+            ('A', true, Some(VarValue::Scalar(s))) => {
+                let mut a = BTreeMap::new();
+                a.insert("0".to_string(), s.clone());
+                self.value = Some(VarValue::Assoc(a));
+            },
+            _ => {},
+        }
+    }
+
+    /* "set" quotes scalars in the single quoted form, "declare -p" in the double quoted form */
+    pub fn value_text(&self, single_quote: bool) -> Option<String> {
+        let q = |s: &str| if single_quote { quote(s) }else{ dquote(s) };
+
+        match &self.value {
+            Some(VarValue::Scalar(s)) => Some(q(s)),
+            Some(VarValue::Array(a))  => {
+                let elems = a.iter()
+                    .map(|(k, v)| format!("[{}]={}", k, dquote(v)))
+                    .collect::<Vec<String>>();
+                Some(format!("({})", elems.join(" ")))
+            },
+            Some(VarValue::Assoc(a))  => {
+                let elems = a.iter()
+                    .map(|(k, v)| format!("[{}]={}", quote(k), dquote(v)))
+                    .collect::<Vec<String>>();
+                Some(format!("({})", elems.join(" ")))
+            },
+            None => None,
+        }
+    }
+
+    pub fn declare_text(&self, name: &str) -> String {
+        let flags = "aAilnrtux".chars()
+            .filter(|c| match (c, &self.value) {
+                ('a', Some(VarValue::Array(_))) => true,
+                ('A', Some(VarValue::Assoc(_))) => true,
+                _                               => self.has_attr(*c),
+            })
+            .collect::<String>();
+        let flags = if flags.is_empty() { "-".to_string() }else{ flags };
+
+        match self.value_text(false) {
+            Some(v) => format!("declare -{} {}={}", flags, name, v),
+            None    => format!("declare -{} {}", flags, name),
+        }
+    }
+
+    pub fn scalar(&self) -> Option<String> {
+        match &self.value {
+            Some(VarValue::Scalar(s)) => Some(s.clone()),
+            Some(VarValue::Array(a))  => a.get(&0).cloned(),
+            Some(VarValue::Assoc(a))  => a.get("0").cloned(),
+            None                      => None,
+        }
+    }
+
+    pub fn elements(&self) -> Vec<String> {
+        match &self.value {
+            Some(VarValue::Scalar(s)) => vec![s.clone()],
+            Some(VarValue::Array(a))  => a.values().cloned().collect(),
+            Some(VarValue::Assoc(a))  => a.values().cloned().collect(),
+            None                      => vec![],
+        }
+    }
+
+    pub fn element(&self, index: &str) -> Option<String> {
+        match &self.value {
+            Some(VarValue::Assoc(a)) => a.get(index).cloned(),
+            Some(VarValue::Array(a)) => {
+                let n = array_index(index, a.keys().last().map(|k| k+1).unwrap_or(0))?;
+                a.get(&n).cloned()
+            },
+            Some(VarValue::Scalar(s)) => match array_index(index, 1) {
+                Some(0) => Some(s.clone()),
+                _       => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn set_element(&mut self, index: &str, value: String) -> Result<(), String> {
+        if let Some(VarValue::Assoc(a)) = &mut self.value {
+            a.insert(index.to_string(), value);
+            return Ok(());
+        }
+
+        if let Some(VarValue::Scalar(_)) | None = self.value {
+            self.set_attr('a', true);
+        }
+
+        if let Some(VarValue::Array(a)) = &mut self.value {
+            let len = a.keys().last().map(|k| k+1).unwrap_or(0);
+            match array_index(index, len) {
+                Some(n) => { a.insert(n, value); },
+                None    => return Err(format!("{}: bad array subscript", index)),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unset_element(&mut self, index: &str) {
+        match &mut self.value {
+            Some(VarValue::Assoc(a)) => { a.remove(index); },
+            Some(VarValue::Array(a)) => {
+                let len = a.keys().last().map(|k| k+1).unwrap_or(0);
+                if let Some(n) = array_index(index, len) {
+                    a.remove(&n);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/* negative indexes count from the end of the array */
+fn array_index(index: &str, len: usize) -> Option<usize> {
+    let n = index.trim().parse::<i64>().ok()?;
+    if n >= 0 {
+        Some(n as usize)
+    }else if (-n) as usize <= len {
+        Some(len - (-n) as usize)
+    }else{
+        None
+    }
+}
+
+pub enum Scope {
+    Visible,
+    Local,
+    Global,
+    Temp,
+}
+
+#[derive(Debug, Default)]
+struct Frame {
+    vars: HashMap<String, Variable>,
+    saved_flags: Option<String>,
+    temp: bool, // for assignments preceding a builtin or a function
+}
+
+#[derive(Debug)]
+pub struct Variables {
+    frames: Vec<Frame>, // frames[0]: global variables, others: local variables of function calls
+}
+
+impl Variables {
+    pub fn new() -> Variables {
+        Variables { frames: vec![Frame::default()] }
+    }
+
+    pub fn push_frame(&mut self) {
+        self.frames.push(Frame::default());
+    }
+
+    pub fn push_temp_frame(&mut self) {
+        self.frames.push(Frame{temp: true, ..Default::default()});
+    }
+
+    /* returns the option flags saved with "local -" */
+    pub fn pop_frame(&mut self) -> Option<String> {
+        if self.frames.len() > 1 {
+            if let Some(f) = self.frames.pop() {
+                return f.saved_flags;
+            }
+        }
+        None
+    }
+
+    pub fn in_function(&self) -> bool {
+        self.frames[1..].iter().any(|f| ! f.temp)
+    }
+
+    fn local_frame(&self) -> usize {
+        self.frames.iter().rposition(|f| ! f.temp).unwrap_or(0)
+    }
+
+    pub fn save_flags(&mut self, flags: &str) {
+        if let Some(f) = self.frames.last_mut() {
+            if f.saved_flags.is_none() {
+                f.saved_flags = Some(flags.to_string());
+            }
+        }
+    }
+
+    fn frame_of(&self, name: &str) -> Option<usize> {
+        self.frames.iter().rposition(|f| f.vars.contains_key(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Variable> {
+        let n = self.frame_of(name)?;
+        self.frames[n].vars.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Variable> {
+        let n = self.frame_of(name)?;
+        self.frames[n].vars.get_mut(name)
+    }
+
+    pub fn get_in(&self, name: &str, scope: &Scope) -> Option<&Variable> {
+        match scope {
+            Scope::Visible => self.get(name),
+            Scope::Local   => self.frames[self.local_frame()].vars.get(name),
+            Scope::Global  => self.frames[0].vars.get(name),
+            Scope::Temp    => self.frames.last()?.vars.get(name),
+        }
+    }
+
+    pub fn entry(&mut self, name: &str, scope: &Scope) -> &mut Variable {
+        let n = match scope {
+            Scope::Visible => self.frame_of(name).unwrap_or(0),
+            Scope::Local   => self.local_frame(),
+            Scope::Global  => 0,
+            Scope::Temp    => self.frames.len() - 1,
+        };
+        self.frames[n].vars.entry(name.to_string()).or_insert(Variable::new())
+    }
+
+    /* a local variable unset in its own function stays as an unset one not to expose the caller's */
+    pub fn remove(&mut self, name: &str) -> Option<Variable> {
+        let n = self.frame_of(name)?;
+        if n > 0 && n == self.local_frame() {
+            return self.frames[n].vars.insert(name.to_string(), Variable::new());
+        }
+        self.frames[n].vars.remove(name)
+    }
+
+    /* NAME=value pairs passed to external commands */
+    pub fn exported(&self) -> Vec<(String, String)> {
+        let mut ans = vec![];
+        for name in self.names() {
+            if let Some(v) = self.get(&name) {
+                if let (true, Some(s)) = (v.has_attr('x'), v.scalar()) {
+                    ans.push( (name, s) );
+                }
+            }
+        }
+        ans
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut ans: Vec<String> = self.frames.iter()
+            .flat_map(|f| f.vars.keys().cloned())
+            .filter(|k| k.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+            .collect();
+        ans.sort();
+        ans.dedup();
+        ans
+    }
+}
@@ -3,7 +3,7 @@
 
 use crate::Feeder;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DebugInfo {
     pub lineno: u32,
     pub pos: u32,
@@ -8,6 +8,8 @@ pub mod double_paren;
 pub mod if_command;
 pub mod case_command;
 pub mod while_command;
+pub mod select_command;
+pub mod coproc;
 pub mod function_definition;
 
 use nix::unistd::Pid;
@@ -15,11 +17,12 @@ use nix::unistd::Pid;
 use std::os::unix::prelude::RawFd;
 
 use crate::{Feeder, ShellCore}; 
-use crate::core::proc;
 
 use self::double_paren::CommandDoubleParen;
 use self::if_command::CommandIf;
 use self::while_command::CommandWhile;
+use self::select_command::CommandSelect;
+use self::coproc::CommandCoproc;
 use self::paren::CommandParen;
 use self::brace::CommandBrace;
 use self::case_command::CommandCase;
@@ -53,7 +56,19 @@ impl Debug for dyn Command {
     }
 }
 
-pub trait Command {
+pub trait CommandClone {
+    fn boxed_clone(&self) -> Box<dyn Command>;
+}
+
+impl<T: 'static + Command + Clone> CommandClone for T {
+    fn boxed_clone(&self) -> Box<dyn Command> { Box::new(self.clone()) }
+}
+
+impl Clone for Box<dyn Command> {
+    fn clone(&self) -> Box<dyn Command> { self.boxed_clone() }
+}
+
+pub trait Command: CommandClone {
     fn exec(&mut self, core: &mut ShellCore) {
         if self.no_connection() {
              self.exec_elems(core);
@@ -62,8 +77,7 @@ pub trait Command {
 
         match unsafe{fork()} {
             Ok(ForkResult::Child) => {
-                core.set_var("BASHPID", &nix::unistd::getpid().to_string());
-                proc::set_signals();
+                core.initialize_as_subshell();
                 self.set_group();
                 /*
                 if self.is_group_leader() { //TODO: implement this function
@@ -75,7 +89,7 @@ pub trait Command {
                 }
                 self.exec_elems(core);
                 close(1).expect("Can't close a pipe end");
-                exit(core.vars["?"].parse::<i32>().unwrap());
+                exit(core.get_var("?").parse::<i32>().unwrap());
             },
             Ok(ForkResult::Parent { child } ) => {
                 self.set_pid(child);
@@ -103,6 +117,8 @@ pub trait Command {
 pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Command>> {
     if let Some(a) =      CommandIf::parse(text,core)                  {Some(Box::new(a))}
     else if let Some(a) = CommandWhile::parse(text, core)              {Some(Box::new(a))}
+    else if let Some(a) = CommandSelect::parse(text, core)             {Some(Box::new(a))}
+    else if let Some(a) = CommandCoproc::parse(text, core)             {Some(Box::new(a))}
     else if let Some(a) = CommandCase::parse(text, core)               {Some(Box::new(a))}
     else if let Some(a) = CommandDoubleParen::parse(text, core, false) {Some(Box::new(a))}
     else if let Some(a) = CommandParen::parse(text, core, false)       {Some(Box::new(a))}
@@ -25,7 +25,7 @@ fn tail_check(s: &String) -> bool{
     false
 }*/
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandBrace {
     pub script: Option<Script>,
     text: String,
@@ -39,7 +39,7 @@ impl Command for CommandBrace {
     fn exec_elems(&mut self, core: &mut ShellCore) {
              self.script.as_mut().unwrap().exec(core);
              if ! self.fds.no_connection() {
-                 exit(core.vars["?"].parse::<i32>().unwrap());
+                 exit(core.get_var("?").parse::<i32>().unwrap());
              }
     }
 
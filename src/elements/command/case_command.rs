@@ -1,7 +1,7 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{ShellCore, Feeder};
+use crate::ShellCore;
 use crate::elements::command::Command;
 use std::os::unix::prelude::RawFd;
 //use crate::operators::ControlOperator;
@@ -12,10 +12,12 @@ use nix::unistd;
 use crate::file_descs::*;
 //use crate::feeder::scanner::*;
 use crate::elements::word::Word;
-use crate::bash_glob::glob_match;
+use crate::bash_glob::{glob_match, GlobOptions};
+use crate::utils::quote;
+use crate::Feeder;
 // use crate::elements::CommandElem;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandCase {
     pub word: Word,
     pub pattern_and_script: Vec<(Vec<String>, Script, String)>,
@@ -54,15 +56,23 @@ impl Command for CommandCase {
     fn get_text(&self) -> String { self.text.clone() }
 
     fn exec_elems(&mut self, core: &mut ShellCore) {
-        let word_str = self.word.eval(core).join(" ");
+        let word_str = Word::remove_escape(&self.word.eval(core).join(" "));
+        core.xtrace(&format!("case {} in", quote(&word_str)));
 
+        let opts = GlobOptions {
+            nocase: core.shopts.get("nocasematch"),
+            ..GlobOptions::new(&core.shopts)
+        };
+
+        core.set_var("?", "0");
         let mut do_next = false;
         for (cond, doing, end) in &mut self.pattern_and_script {
             let mut matched = false;
             for c in cond {
-                if do_next || glob_match(c, &word_str) {
+                if do_next || glob_match(&Self::expand_pattern(c, core), &word_str, &opts) {
                     do_next = false;
 
+                    core.set_var("?", "0");
                     doing.exec(core);
                     matched = true;
 
@@ -73,7 +83,7 @@ impl Command for CommandCase {
                     break;
                 }
             }
-            if end == &";;" && matched {
+            if end != ";;&" && matched && ! do_next {
                 break;
             }
         }
@@ -92,13 +102,27 @@ impl CommandCase {
         }
     }
 
+    /* quoted or escaped characters stay escaped so that they match literally */
+    fn expand_pattern(pattern: &str, core: &mut ShellCore) -> String {
+        let mut feeder = Feeder::new_from(pattern.trim_start().to_string());
+        let word = Word::parse(&mut feeder, core, false);
+        feeder.consume_blank();
+
+        match word {
+            Some(mut w) if feeder.len() == 0 => w.eval(core).join(" "),
+            _ => pattern.to_string(),
+        }
+    }
 
     fn parse_cond_do_pair(text: &mut Feeder, core: &mut ShellCore, ans: &mut CommandCase) -> bool {
         let mut conds = vec![];
         ans.text += &text.request_next_line(core);
+        if text.starts_with("(") {
+            ans.text += &text.consume(1);
+        }
 
         loop {
-            let pos = text.scanner_until_escape("|)");
+            let pos = text.scanner_case_pattern();
             if pos == 0 || pos == text.len() {
                 core.nest.pop();
                 return false;
@@ -129,9 +153,11 @@ impl CommandCase {
         }else if text.starts_with(";&") {
             ans.text += &text.consume(2);
             ";&".to_string()
-        }else {
+        }else if text.starts_with(";;") {
             ans.text += &text.consume(2);
             ";;".to_string()
+        }else{ // the last pattern before esac
+            "".to_string()
         };
 
         ans.pattern_and_script.push( (conds, doing, end) );
@@ -0,0 +1,137 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::command;
+use crate::elements::command::Command;
+use crate::elements::command::simple::SimpleCommand;
+use crate::file_descs::FileDescs;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+use nix::unistd::{close, fork, pipe, ForkResult, Pid};
+use nix::unistd;
+use std::os::unix::prelude::RawFd;
+use std::process::exit;
+
+#[derive(Debug, Clone)]
+pub struct CommandCoproc {
+    pub name: String,
+    pub command: Option<Box<dyn Command>>,
+    text: String,
+}
+
+impl Command for CommandCoproc {
+    fn exec(&mut self, core: &mut ShellCore) {
+        let to_coproc = pipe().expect("Pipe cannot open");
+        let from_coproc = pipe().expect("Pipe cannot open");
+
+        match unsafe{fork()} {
+            Ok(ForkResult::Child) => {
+                core.initialize_as_subshell();
+                let pid = nix::unistd::getpid();
+                let _ = unistd::setpgid(pid, pid);
+
+                close(to_coproc.1).expect("Can't close a pipe end");
+                close(from_coproc.0).expect("Can't close a pipe end");
+                FileDescs::dup_and_close(to_coproc.0, 0);
+                FileDescs::dup_and_close(from_coproc.1, 1);
+
+                if let Some(c) = &mut self.command {
+                    c.exec(core);
+                    core.jobs.set_fg_job(&self.text, &vec![c.clone()]);
+                    core.wait_job();
+                }
+                exit(core.get_var("?").parse::<i32>().unwrap_or(1));
+            },
+            Ok(ForkResult::Parent { child } ) => {
+                close(to_coproc.0).expect("Can't close a pipe end");
+                close(from_coproc.1).expect("Can't close a pipe end");
+                self.set_parent_side(core, child, from_coproc.0, to_coproc.1);
+            },
+            Err(err) => panic!("Failed to fork. {}", err),
+        }
+    }
+
+    fn get_pid(&self) -> Option<Pid> { None } // not waited as a foreground job
+    fn set_group(&mut self){}
+    fn set_group_leader(&mut self) {}
+    fn set_pipe(&mut self, _pin: RawFd, _pout: RawFd, _pprev: RawFd) {}
+    fn get_text(&self) -> String { self.text.clone() }
+}
+
+impl CommandCoproc {
+    pub fn new() -> CommandCoproc {
+        CommandCoproc {
+            name: "COPROC".to_string(),
+            command: None,
+            text: String::new(),
+        }
+    }
+
+    fn set_parent_side(&mut self, core: &mut ShellCore, child: Pid, rfd: RawFd, wfd: RawFd) {
+        for fd in [rfd, wfd] { // not to be inherited by commands executed later
+            let _ = fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC));
+        }
+
+        let mut com = SimpleCommand::new();
+        com.pid = Some(child);
+        core.jobs.add_bg_job(&self.text, &vec!(Box::new(com)));
+        if let Some(job) = core.jobs.backgrounds.last_mut() {
+            job.coproc = Some((self.name.clone(), vec![rfd, wfd]));
+        }
+
+        core.set_array(&self.name, vec![(None, rfd.to_string()), (None, wfd.to_string())]);
+        core.set_var(&(self.name.clone() + "_PID"), &child.to_string());
+        core.set_var("?", "0");
+    }
+
+    /* NAME is only accepted before a compound command like bash */
+    fn eat_name(text: &mut Feeder, ans: &mut CommandCoproc) {
+        let backup = text.clone();
+        let len = text.scanner_name();
+        if len == 0 {
+            return;
+        }
+
+        let name = text.consume(len);
+        let blank = text.consume_blank();
+        let compound = ["{", "(", "if", "while", "case", "select"].iter()
+                       .any(|w| text.starts_with(w));
+        if blank.is_empty() || ! compound {
+            text.rewind(backup);
+            return;
+        }
+
+        ans.text += &name;
+        ans.text += &blank;
+        ans.name = name;
+    }
+
+    pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<CommandCoproc> {
+        if text.len() < 7 || ! text.starts_with("coproc") {
+            return None;
+        }
+
+        let backup = text.clone();
+        let mut ans = CommandCoproc::new();
+        ans.text += &text.consume(6);
+        let blank = text.consume_blank();
+        if blank.is_empty() {
+            text.rewind(backup);
+            return None;
+        }
+        ans.text += &blank;
+
+        CommandCoproc::eat_name(text, &mut ans);
+        match command::parse(text, core) {
+            Some(c) => {
+                ans.text += &c.get_text();
+                ans.command = Some(c);
+                Some(ans)
+            },
+            None => {
+                text.rewind(backup);
+                None
+            },
+        }
+    }
+}
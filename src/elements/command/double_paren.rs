@@ -11,7 +11,7 @@ use crate::file_descs::*;
 use crate::calculator::calculate;
 use nix::unistd;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandDoubleParen {
     text: String,
     expression: String,
@@ -24,6 +24,9 @@ pub struct CommandDoubleParen {
 
 impl Command for CommandDoubleParen {
     fn exec(&mut self, core: &mut ShellCore) {
+        if ! self.substitution {
+            core.xtrace(&format!("(( {} ))", self.expression.trim()));
+        }
         self.substitution_text = calculate(self.expression.clone(), core);
 
         let status = if self.substitution_text == "0" {
@@ -71,6 +74,23 @@ impl CommandDoubleParen {
         }
     }
 
+    /* position of the first ")" out of parentheses in the expression */
+    fn scanner_expression_end(text: &Feeder) -> usize {
+        let mut depth = 0;
+        let mut pos = 0;
+        for ch in text.chars_after(0) {
+            if ch == '(' {
+                depth += 1;
+            }else if ch == ')' && depth == 0 {
+                break;
+            }else if ch == ')' {
+                depth -= 1;
+            }
+            pos += ch.len_utf8();
+        }
+        pos
+    }
+
     // TODO: this function must parse ((1+$(echo a | wc -l))) for example. 
     pub fn parse(text: &mut Feeder, core: &mut ShellCore, substitution: bool) -> Option<CommandDoubleParen> {
         if text.len() < 2 || ! text.starts_with( "((") {
@@ -84,7 +104,7 @@ impl CommandDoubleParen {
         loop{
             ans.text = text.consume(2);
 
-            let pos = text.scanner_until(0, ")");
+            let pos = Self::scanner_expression_end(text);
             if text.len() > pos+1 && text.nth(pos) == ')' && text.nth(pos+1) != ')' {
                 text.rewind(backup);
                 return None;
@@ -10,8 +10,33 @@ use nix::unistd::Pid;
 use nix::unistd;
 use std::os::unix::prelude::RawFd;
 use crate::FileDescs;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 #[derive(Debug)]
+pub struct ShellFunction {
+    pub body: Box<dyn Command>,
+    idle_bodies: RefCell<Vec<Box<dyn Command>>>, // copies of the body reused by calls
+    pub text: String,
+    pub source: String,
+    pub lineno: u32,
+}
+
+impl ShellFunction {
+    /* a copy is made only when every copy is running in recursive calls */
+    pub fn take_body(&self) -> Box<dyn Command> {
+        match self.idle_bodies.borrow_mut().pop() {
+            Some(b) => b,
+            None    => self.body.clone(),
+        }
+    }
+
+    pub fn return_body(&self, body: Box<dyn Command>) {
+        self.idle_bodies.borrow_mut().push(body);
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FunctionDefinition {
     pub name: String,
     pub body: Box<dyn Command>,
@@ -19,11 +44,20 @@ pub struct FunctionDefinition {
     pub text: String,
     fds: FileDescs,
     group_leader: bool,
+    lineno: u32,
 }
 
 impl Command for FunctionDefinition {
     fn exec_elems(&mut self, conf: &mut ShellCore) {
-        conf.functions.insert(self.name.clone(), self.body.get_text());
+        let source = conf.current_source();
+        let f = ShellFunction {
+            body: self.body.clone(),
+            idle_bodies: RefCell::new(vec![]),
+            text: self.text.clone(),
+            source,
+            lineno: self.lineno,
+        };
+        conf.functions.insert(self.name.clone(), Rc::new(f));
     }
     fn set_pid(&mut self, pid: Pid) { self.pid = Some(pid); }
     fn set_group(&mut self){
@@ -63,11 +97,13 @@ impl FunctionDefinition {
             pid: None,
             fds: FileDescs::new(),
             group_leader: false,
+            lineno: 0,
         }
     }
 
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<FunctionDefinition> {
          let backup = text.clone();
+         let lineno = text.lineno().0;
          let mut ans_text = String::new();
 
          if text.starts_with("function") {
@@ -101,7 +137,8 @@ impl FunctionDefinition {
  
          if let Some(c) = command::parse(text, conf){
              ans_text += &c.get_text();
-             let ans = FunctionDefinition::new(name, c, ans_text);
+             let mut ans = FunctionDefinition::new(name, c, ans_text);
+             ans.lineno = lineno;
              Some( ans )
          }else{
              text.rewind(backup);
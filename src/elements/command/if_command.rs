@@ -10,7 +10,7 @@ use nix::unistd::Pid;
 use crate::file_descs::*;
 use nix::unistd;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandIf {
     pub ifthen: Vec<(Script, Script)>,
     pub else_do: Option<Script>,
@@ -24,7 +24,10 @@ impl Command for CommandIf {
     fn exec_elems(&mut self, core: &mut ShellCore) {
         for pair in self.ifthen.iter_mut() {
              pair.0.exec(core);
-             if core.vars["?"] != "0" {
+             if core.return_flag || core.break_counter > 0 {
+                return;
+             }
+             if core.get_var("?") != "0" {
                 continue;
              }
              pair.1.exec(core);
@@ -13,9 +13,8 @@ use crate::elements::redirect::Redirect;
 use crate::file_descs::*;
 use nix::unistd::{close, pipe};
 //use crate::feeder::scanner::*;
-use crate::core::proc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandParen {
     pub script: Option<Script>,
     text: String,
@@ -32,8 +31,7 @@ impl Command for CommandParen {
 
         match unsafe{fork()} {
             Ok(ForkResult::Child) => {
-                core.set_var("BASHPID", &nix::unistd::getpid().to_string());
-                proc::set_signals();
+                core.initialize_as_subshell();
                 self.set_group();
                 if let Err(s) = self.fds.set_child_io(core){
                     eprintln!("{}", s);
@@ -41,12 +39,13 @@ impl Command for CommandParen {
                 }
                 if let Some(s) = &mut self.script {
                     if self.substitution {
+                        core.comsub_level += 1;
                         close(p.0).expect("Can't close a pipe end");
                         FileDescs::dup_and_close(p.1, 1);
                     }
                     s.exec(core);
                     close(1).expect("Can't close a pipe end");
-                    exit(core.vars["?"].parse::<i32>().unwrap());
+                    exit(core.get_var("?").parse::<i32>().unwrap());
                 };
             },
             Ok(ForkResult::Parent { child } ) => {
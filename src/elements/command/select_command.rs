@@ -0,0 +1,228 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::command::Command;
+use std::io;
+use std::os::unix::prelude::RawFd;
+use crate::elements::script::Script;
+use crate::elements::redirect::Redirect;
+use crate::elements::word::Word;
+use crate::utils::{align_elems_on_term, eval_glob};
+use nix::unistd::Pid;
+use nix::unistd;
+use crate::file_descs::*;
+
+#[derive(Debug, Clone)]
+pub struct CommandSelect {
+    pub name: String,
+    pub words: Option<Vec<Word>>, // None means "$@"
+    pub doing: Script,
+    text: String,
+    pid: Option<Pid>,
+    fds: FileDescs,
+    group_leader: bool,
+}
+
+impl Command for CommandSelect {
+    fn get_pid(&self) -> Option<Pid> { self.pid }
+    fn set_pid(&mut self, pid: Pid) { self.pid = Some(pid); }
+    fn set_group(&mut self){
+        if self.group_leader {
+            let pid = nix::unistd::getpid();
+            let _ = unistd::setpgid(pid, pid);
+        }
+    }
+    fn set_group_leader(&mut self) { self.group_leader = true; }
+    fn no_connection(&self) -> bool { self.fds.no_connection() }
+
+    fn set_pipe(&mut self, pin: RawFd, pout: RawFd, pprev: RawFd) {
+        self.fds.pipein = pin;
+        self.fds.pipeout = pout;
+        self.fds.prevpipein = pprev;
+    }
+
+    fn set_child_io(&mut self, core: &mut ShellCore) -> Result<(), String> {
+        self.fds.set_child_io(core)
+    }
+
+    fn get_text(&self) -> String { self.text.clone() }
+
+    fn exec_elems(&mut self, core: &mut ShellCore) {
+        let list = match self.eval_words(core) {
+            Some(l) => l,
+            None    => {
+                core.set_var("?", "1");
+                return;
+            },
+        };
+
+        core.set_var("?", "0");
+        if list.is_empty() {
+            return;
+        }
+
+        core.loop_level += 1;
+        Self::print_menu(&list, core);
+        loop {
+            let ps3 = core.get_var("PS3");
+            eprint!("{}", if ps3.is_empty() { "#? " }else{ &ps3 });
+
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+                eprintln!();
+                core.set_var("?", "1");
+                break;
+            }
+
+            let reply = line.trim_end_matches('\n');
+            core.set_var("REPLY", reply);
+            if reply.trim() == "" {
+                Self::print_menu(&list, core);
+                continue;
+            }
+
+            let value = match reply.trim().parse::<usize>() {
+                Ok(n) if n >= 1 && n <= list.len() => list[n-1].clone(),
+                _ => String::new(),
+            };
+            core.set_var(&self.name, &value);
+
+            self.doing.exec(core);
+            if core.return_flag || core.discard_flag {
+                break;
+            }
+            if core.break_counter > 0 {
+                core.break_counter -= 1;
+                break;
+            }
+        }
+        core.loop_level -= 1;
+    }
+}
+
+impl CommandSelect {
+    fn eval_words(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
+        let words = match &mut self.words {
+            Some(ws) => ws,
+            None     => return Some(core.args.iter().skip(1).cloned().collect()),
+        };
+
+        let mut ans = vec![];
+        for w in words.iter_mut() {
+            for s in w.eval(core) {
+                match eval_glob(&s, core) {
+                    Ok(gs) => ans.extend(gs.iter().map(Word::remove_escape)),
+                    Err(msg) => {
+                        eprintln!("{}", msg);
+                        return None;
+                    },
+                }
+            }
+        }
+        Some(ans)
+    }
+
+    /* the menu is in a column unless it fits in a line like bash */
+    fn print_menu(list: &[String], core: &mut ShellCore) {
+        let digits = list.len().to_string().len();
+        let items = list.iter().enumerate()
+            .map(|(i, w)| format!("{:>width$}) {}", i+1, w, width=digits))
+            .collect::<Vec<String>>();
+
+        let columns = core.get_var("COLUMNS").parse::<u32>().unwrap_or(80);
+        let menu = align_elems_on_term(&items, columns);
+        if menu.matches("\r\n").count() <= 1 {
+            items.iter().for_each(|i| eprintln!("{}", i));
+        }else{
+            menu.split("\r\n").filter(|l| ! l.is_empty()).for_each(|l| eprintln!("{}", l.trim_end()));
+        }
+    }
+
+    pub fn new() -> CommandSelect {
+        CommandSelect {
+            name: String::new(),
+            words: None,
+            doing: Script::new(),
+            text: String::new(),
+            fds: FileDescs::new(),
+            pid: None,
+            group_leader: false,
+        }
+    }
+
+    fn eat_words(text: &mut Feeder, core: &mut ShellCore, ans: &mut CommandSelect) -> bool {
+        if text.len() < 3 || ! text.starts_with("in") || ! " \t;\n".contains(text.nth(2)) {
+            return true;
+        }
+
+        ans.text += &text.consume(2);
+        let mut words = vec![];
+        loop {
+            ans.text += &text.consume_blank();
+            if text.len() == 0 || text.starts_with(";") || text.starts_with("\n") {
+                break;
+            }
+
+            match Word::parse(text, core, false) {
+                Some(w) => {
+                    ans.text += &w.text;
+                    words.push(w);
+                },
+                None => return false,
+            }
+        }
+        ans.words = Some(words);
+        true
+    }
+
+    pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<CommandSelect> {
+        if text.len() < 7 || ! text.starts_with("select") {
+            return None;
+        }
+
+        let backup = text.clone();
+        let mut ans = CommandSelect::new();
+        ans.text += &text.consume(6);
+        let blank = text.consume_blank();
+        let len = text.scanner_name();
+        if blank.is_empty() || len == 0 {
+            text.rewind(backup);
+            return None;
+        }
+        ans.text += &blank;
+        ans.name = text.consume(len);
+        ans.text += &ans.name.clone();
+        ans.text += &text.request_next_line(core);
+
+        if ! CommandSelect::eat_words(text, core, &mut ans) {
+            text.rewind(backup);
+            return None;
+        }
+
+        if text.starts_with(";") {
+            ans.text += &text.consume(1);
+        }
+        ans.text += &text.request_next_line(core);
+        if ! text.starts_with("do") {
+            text.rewind(backup);
+            return None;
+        }
+        ans.text += &text.consume(2);
+
+        core.nest.push("do".to_string());
+        ans.doing = match Script::parse(text, core) {
+            Some(s) => s,
+            None    => {
+                core.nest.pop();
+                return None;
+            },
+        };
+        core.nest.pop();
+        ans.text += &ans.doing.text.clone();
+        ans.text += &text.consume(4); //always "done"
+
+        while Redirect::eat_me(text, core, &mut ans.text, &mut ans.fds) {}
+        Some(ans)
+    }
+}
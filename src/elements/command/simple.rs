@@ -1,8 +1,6 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use std::env;
-
 use nix::unistd::{execvpe, fork, ForkResult, Pid}; 
 use nix::unistd;
 use std::ffi::CString;
@@ -12,72 +10,96 @@ use std::os::unix::prelude::RawFd;
 use crate::{ShellCore,Feeder};
 use crate::utils::*;
 
-use crate::core::proc;
 use crate::elements::command::Command;
-use crate::elements::command;
 use crate::elements::word::Word;
 use crate::elements::redirect::Redirect;
-use crate::elements::substitution::Substitution;
+use crate::elements::substitution::{Substitution, Assignment};
 //use crate::feeder::scanner::*;
 use crate::file_descs::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SimpleCommand {
     vars: Vec<Substitution>,
     pub args: Vec<Word>,
+    array_args: Vec<(usize, Substitution)>, // "name=(...)" given to declaration commands
     pub text: String,
     pub pid: Option<Pid>,
     fds: FileDescs,
     pub group_leader: bool,
+    lineno: u32,
 }
 
 fn is_reserve(s: &String) -> bool {
-    s == "then" || s == "else" || s == "elif" || s == "fi" || s == "done" || s == "do" || s == ";;"
+    s == "then" || s == "else" || s == "elif" || s == "fi" || s == "done" || s == "do" || s == "esac" || s == ";;"
 }
 
 
 impl Command for SimpleCommand {
     fn exec(&mut self, core: &mut ShellCore) {
-        if self.args.len() == 0 && self.fds.no_pipe() {
-            self.set_vars(core);
-        }
-
         if core.has_flag('v') {
             eprintln!("{}", self.text.trim_end());
         }
 
-        let mut args = self.eval(core);
+        core.lineno = self.lineno;
+        if ! self.fds.no_pipe() {
+            self.exec_in_subshell(core);
+            return;
+        }
+
+        let subs = self.eval_vars(core);
+        if self.args.is_empty() && self.fds.no_pipe() && ! self.set_vars(&subs, core) {
+            core.set_var("?", "1");
+            return;
+        }
+
+        let mut args = match self.eval(core) {
+            Some(a) => a,
+            None    => { // failglob
+                core.set_var("?", "1");
+                core.discard_flag = true;
+                return;
+            },
+        };
         //eprintln!("NUM:{} {:?}", args.len(), &args); 
         if args.len() == 0 {
             core.set_var("_", "");
         }else{
             core.set_var("_", &args[args.len()-1]);
-        }
-
-        if core.has_flag('x') {
-            eprintln!("+{}", args.join(" "));
+            Self::xtrace(&subs, &args, core);
         }
 
         // This sentence avoids an unnecessary fork for an internal command.
-        if self.fds.no_connection() && args.len() != 0 {
-            if core.functions.contains_key(&args[0]) {
-                self.exec_function(&mut args, core);
+        if self.fds.no_connection() && ! args.is_empty() && ! self.group_leader {
+            if ! subs.is_empty() && self.is_internal(&args[0], core) {
+                if core.push_temp_scope(&subs) {
+                    self.exec_internal(&mut args, core);
+                }else{
+                    core.set_var("?", "1");
+                }
+                core.pop_scope();
                 return;
             }
-            if self.run_on_this_process(&mut args, core) {
+            if self.exec_internal(&mut args, core) {
                 return;
             }
         }
 
+        if ! args.is_empty() && ! self.is_internal(&args[0], core) {
+            core.search_command(&args[0]); // to remember the path in this process
+        }
+
         match unsafe{fork()} {
             Ok(ForkResult::Child) => {
-                proc::set_signals();
+                core.initialize_as_child();
                 self.set_group();
                 if let Err(s) = self.fds.set_child_io(core){
                     eprintln!("{}", s);
                     exit(1);
                 }
                 if args.len() != 0 {
+                    if ! core.push_temp_scope(&subs) {
+                        exit(1);
+                    }
                     self.exec_external_command(&mut args, core)
                 }else{
                     exit(0);
@@ -118,11 +140,60 @@ impl SimpleCommand {
         SimpleCommand {
             vars: vec![],
             args: vec![],
+            array_args: vec![],
             //eoc: None,
             text: "".to_string(),
             pid: None,
             fds: FileDescs::new(),
             group_leader: false,
+            lineno: 0,
+        }
+    }
+
+    /* a pipeline element expands its words after the fork like bash */
+    fn exec_in_subshell(&mut self, core: &mut ShellCore) {
+        match unsafe{fork()} {
+            Ok(ForkResult::Child) => {
+                core.initialize_as_child();
+                self.set_group();
+                if let Err(s) = self.fds.set_child_io(core){
+                    eprintln!("{}", s);
+                    exit(1);
+                }
+
+                let subs = self.eval_vars(core);
+                let mut args = match self.eval(core) {
+                    Some(a) => a,
+                    None    => exit(1),
+                };
+                if args.is_empty() {
+                    exit( if self.set_vars(&subs, core) {0}else{1} );
+                }
+
+                core.set_var("_", &args[args.len()-1]);
+                Self::xtrace(&subs, &args, core);
+                if ! core.push_temp_scope(&subs) {
+                    exit(1);
+                }
+                self.exec_external_command(&mut args, core)
+            },
+            Ok(ForkResult::Parent { child } ) => {
+                self.pid = Some(child);
+            },
+            Err(err) => panic!("Failed to fork. {}", err),
+        }
+    }
+
+    fn is_internal(&self, name: &String, core: &ShellCore) -> bool {
+        core.functions.contains_key(name) || core.get_builtin(name).is_some()
+    }
+
+    fn exec_internal(&mut self, args: &mut Vec<String>, core: &mut ShellCore) -> bool {
+        if core.functions.contains_key(&args[0]) {
+            self.exec_function(args, core);
+            true
+        }else{
+            self.run_on_this_process(args, core)
         }
     }
 
@@ -136,18 +207,27 @@ impl SimpleCommand {
         }
     }
 
-    fn eval(&mut self, core: &mut ShellCore) -> Vec<String> {
+    fn eval(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
         let mut args = vec![];
 
-        for word in &mut self.args {
+        for (i, word) in self.args.iter_mut().enumerate() {
+            if let Some((_, sub)) = self.array_args.iter_mut().find(|a| a.0 == i) {
+                args.push(sub.eval(core).text());
+                continue;
+            }
+
             for s in &word.eval(core) {
-                args.append(&mut eval_glob(&s.clone()));
+                match eval_glob(&s.clone(), core) {
+                    Ok(gs) => args.extend(gs.iter().map(Word::remove_escape)),
+                    Err(msg) => {
+                        eprintln!("{}", msg);
+                        return None;
+                    },
+                }
             }
         };
 
-        args.iter()
-            .map(|a| Word::remove_escape(&a))
-            .collect()
+        Some(args)
     }
 
     pub fn push_vars(&mut self, s: Substitution){
@@ -165,29 +245,38 @@ impl SimpleCommand {
     }
 
     fn exec_function(&mut self, args: &mut Vec<String>, core: &mut ShellCore) {
-        let text = core.get_function(&args[0]).unwrap();
-
-        let mut feeder = Feeder::new_from(text);
-        //dbg!("IN '{:?}'", &feeder);
-        if let Some(mut f) = command::parse(&mut feeder, core) {
-         //   eprintln!("FUNCTION '{:?}'", f);
-            let backup = core.args.clone();
-            core.args = args.to_vec();
-            core.return_enable = true;
-            f.exec(core);
-            self.pid = f.get_pid();
-            core.args = backup;
-            core.return_enable = false;
-        }else{
-            panic!("Shell internal error on function");
+        let level = core.function_level();
+        if ! core.check_nest(&args[0], "function", level) {
+            return;
+        }
+
+        let f = match core.get_function(&args[0]) {
+            Some(f) => f,
+            None    => panic!("Shell internal error on function"),
         };
-        //eprintln!("OUT '{}'", feeder._text());
+        let mut body = f.take_body();
+
+        let backup = core.args.clone();
+        let loop_level = core.loop_level;
+        core.args = args.to_vec();
+        core.loop_level = 0;
+        core.push_scope();
+        core.push_call(&args[0], &f.source, &args[1..]);
+        body.exec(core);
+        core.return_flag = false;
+        core.pop_call();
+        core.pop_scope();
+        self.pid = body.get_pid();
+        f.return_body(body);
+        core.args = backup;
+        core.loop_level = loop_level;
     }
 
     fn exec_external_command(&mut self, args: &mut Vec<String>, core: &mut ShellCore) {
         if core.functions.contains_key(&args[0]) {
+            core.subshell_level += 1;
             self.exec_function(args, core);
-            exit(0);
+            exit(core.get_var("?").parse::<i32>().unwrap_or(1));
         }
 
         if let Some(func) = core.get_builtin(&args[0]) {
@@ -195,7 +284,7 @@ impl SimpleCommand {
         }
 
         let org = args[0].clone();
-        args[0] = get_fullpath(&org);
+        args[0] = core.search_command(&org);
         if args[0].len() == 0 {
             eprintln!("Command not found: {:?}", &org);
             exit(127);
@@ -210,14 +299,12 @@ impl SimpleCommand {
             eprintln!("{}", self.parse_info().join("\n"));
         };
 
-        for v in &mut self.vars {
-            let key = (*v).name.clone();
-            let value =  (*v).value.eval(core).join(" ");
-            env::set_var(key, value);
-        }
-        env::set_var("_".to_string(), args[0].clone());
+        let mut envs = core.vars.exported();
+        envs.retain(|v| v.0 != "_");
+        envs.push( ("_".to_string(), args[0].clone()) );
+        envs.append(&mut core.function_envs());
 
-        let envs: Vec<CString> = std::env::vars()
+        let envs: Vec<CString> = envs.iter()
             .map(|v| format!("{}={}", v.0, v.1))
             .map(|a| CString::new(a.to_string()).unwrap())
             .collect();
@@ -270,6 +357,10 @@ impl SimpleCommand {
             if let Some(r) = Redirect::parse(text, core){
                 ans.text += &r.text;
                 ans.fds.redirects.push(Box::new(r));
+            }else if let Some(s) = Self::parse_array_arg(text, core, ans) {
+                ans.text += &s.get_text();
+                ans.args.push(Word{text: s.get_text(), pos: s.debug.clone(), subwords: vec![]});
+                ans.array_args.push( (ans.args.len()-1, s) );
             }else if let Some(a) = Word::parse(text, core, false) {
                 if ! SimpleCommand::ng_check(&a.text, ans.args.len() == 0){
                     text.rewind(backup);
@@ -307,9 +398,26 @@ impl SimpleCommand {
         ok
     }
 
+    fn parse_array_arg(text: &mut Feeder, core: &mut ShellCore, ans: &SimpleCommand) -> Option<Substitution> {
+        match ans.args.first() {
+            Some(w) if ["declare", "typeset", "local", "readonly", "export"].contains(&w.text.as_str()) => {},
+            _ => return None,
+        }
+
+        let backup = text.clone();
+        match Substitution::parse(text, core) {
+            Some(s) if s.array.is_some() => Some(s),
+            _ => {
+                text.rewind(backup);
+                None
+            },
+        }
+    }
+
     pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<SimpleCommand> {
         let backup = text.clone();
         let mut ans = SimpleCommand::new();
+        ans.lineno = text.lineno().0;
 
         if text.starts_with("{") {
             return None;
@@ -328,15 +436,26 @@ impl SimpleCommand {
         }
     }
 
-    fn set_vars(&mut self, core: &mut ShellCore){
-        for e in &mut self.vars {
-            let sub = e.eval(core);
-            let (key, value) = (sub[0].clone(), sub[1].clone());
-            if let Ok(_) = env::var(&key) {
-                env::set_var(key, value);
-            }else{
-                core.set_var(&key, &value);
-            };
+    fn eval_vars(&mut self, core: &mut ShellCore) -> Vec<Assignment> {
+        self.vars.iter_mut().map(|e| e.eval(core)).collect()
+    }
+
+    fn set_vars(&mut self, subs: &Vec<Assignment>, core: &mut ShellCore) -> bool {
+        let mut ok = true;
+        for a in subs {
+            core.xtrace(&a.text());
+            ok &= a.apply(core);
         };
+        ok
+    }
+
+    fn xtrace(subs: &[Assignment], args: &[String], core: &mut ShellCore) {
+        if ! core.has_flag('x') {
+            return;
+        }
+
+        let mut words: Vec<String> = subs.iter().map(|a| a.text()).collect();
+        words.extend(args.iter().map(|a| quote(a)));
+        core.xtrace(&words.join(" "));
     }
 }
@@ -10,7 +10,7 @@ use nix::unistd::Pid;
 use nix::unistd;
 use crate::file_descs::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandWhile {
     pub conddo: Option<(Script, Script)>,
     text: String,
@@ -48,16 +48,32 @@ impl Command for CommandWhile {
     fn get_text(&self) -> String { self.text.clone() }
 
     fn exec_elems(&mut self, core: &mut ShellCore) {
+        core.loop_level += 1;
         loop {
             if let Some((cond, doing)) = &mut self.conddo {
                 cond.exec(core);
-                if core.vars["?"] != "0" {
+                if core.return_flag || core.discard_flag {
+                    break;
+                }
+                if core.break_counter > 0 {
+                    core.break_counter -= 1;
+                    break;
+                }
+                if core.get_var("?") != "0" {
                     core.set_var("?", "0");
                     break;
                 }
                 doing.exec(core);
+                if core.return_flag || core.discard_flag {
+                    break;
+                }
+                if core.break_counter > 0 {
+                    core.break_counter -= 1;
+                    break;
+                }
             }
         }
+        core.loop_level -= 1;
     }
 }
 
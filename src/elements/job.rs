@@ -3,15 +3,17 @@
 
 use crate::{ShellCore, Feeder};
 use crate::elements::pipeline::Pipeline;
-use crate::utils::blue_string;
+use crate::utils::{blue_string, format_times};
 use crate::core::proc;
+use nix::sys::resource::UsageWho;
+use std::time::{Duration, Instant};
 use nix::unistd::{ForkResult};
 use nix::unistd;
 use std::process::exit;
 use super::command::simple::SimpleCommand;
 use crate::operators::ControlOperator;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Job {
     pub pipelines: Vec<Pipeline>,
     pub pipeline_ends: Vec<ControlOperator>,
@@ -33,9 +35,53 @@ impl Job {
                 eop = self.pipeline_ends[i].clone();
                 continue;
             }
-            self.pipelines[i].exec(core);
-            self.set_job_and_wait(i, core);
+            let start = match self.pipelines[i].time_flag {
+                true  => Some( (Instant::now(), Self::cpu_times()) ),
+                false => None,
+            };
+
+            if ! self.pipelines[i].commands.is_empty() {
+                core.reap_coprocs();
+                self.pipelines[i].exec(core);
+                self.set_job_and_wait(i, core);
+            }else{ // only "time"
+                core.set_var("?", "0");
+            }
+
+            if let Some(s) = start {
+                Self::report_time(self.pipelines[i].posix_time, s, core);
+            }
             eop = self.pipeline_ends[i].clone();
+
+            if core.return_flag || core.discard_flag || core.break_counter > 0 {
+                return;
+            }
+        }
+    }
+
+    fn cpu_times() -> (Duration, Duration) {
+        let (su, ss) = proc::cpu_times(UsageWho::RUSAGE_SELF);
+        let (cu, cs) = proc::cpu_times(UsageWho::RUSAGE_CHILDREN);
+        (su + cu, ss + cs)
+    }
+
+    fn report_time(posix: bool, start: (Instant, (Duration, Duration)), core: &mut ShellCore) {
+        let real = start.0.elapsed();
+        let (user, sys) = Self::cpu_times();
+        let (user, sys) = (user.saturating_sub(start.1.0), sys.saturating_sub(start.1.1));
+
+        let format = if posix {
+            "real %2R\nuser %2U\nsys %2S".to_string()
+        }else if core.is_set("TIMEFORMAT") {
+            core.get_var("TIMEFORMAT")
+        }else{
+            "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS".to_string()
+        };
+
+        match format_times(&format, real, user, sys) {
+            Ok(s) if s.is_empty() => {},
+            Ok(s)  => eprintln!("{}", s),
+            Err(c) => eprintln!("bash: TIMEFORMAT: `{}': invalid format character", c),
         }
     }
 
@@ -66,14 +112,13 @@ impl Job {
     fn exec_and_or_bg_job(&mut self, core: &mut ShellCore) {
         match unsafe{unistd::fork()} {
             Ok(ForkResult::Child) => {
-                core.set_var("BASHPID", &nix::unistd::getpid().to_string());
-                proc::set_signals();
+                core.initialize_as_subshell();
                 let pid = nix::unistd::getpid();
                 let _ = unistd::setpgid(pid, pid);
 
                 self.exec(core);
 
-                exit(core.vars["?"].parse::<i32>().unwrap());
+                exit(core.get_var("?").parse::<i32>().unwrap());
             },
             Ok(ForkResult::Parent { child } ) => {
                 let mut com = SimpleCommand::new();
@@ -7,13 +7,15 @@ use crate::operators::ControlOperator;
 use nix::unistd::{pipe,close};
 use crate::elements::command;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pipeline {
     pub commands: Vec<Box<dyn Command>>,
     pub text: String,
     pub is_bg: bool,
     pub job_no: u32,
     pub not_flag: bool,
+    pub time_flag: bool,
+    pub posix_time: bool, // time -p
 }
 
 impl Pipeline {
@@ -58,6 +60,8 @@ impl Pipeline {
             commands: vec![],
             text: "".to_string(),
             not_flag: false,
+            time_flag: false,
+            posix_time: false,
             is_bg: false,
             job_no: 0,
         }
@@ -77,7 +81,18 @@ impl Pipeline {
     pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<Pipeline> {
         let mut ans = Pipeline::new();
         ans.text += &text.consume_blank();
-        if text.starts_with("!") {
+        if Self::starts_with_word(text, "time") {
+            ans.time_flag = true;
+            ans.text += &text.consume(4);
+            ans.text += &text.consume_blank();
+            if Self::starts_with_word(text, "-p") {
+                ans.posix_time = true;
+                ans.text += &text.consume(2);
+                ans.text += &text.consume_blank();
+            }
+        }
+
+        if text.starts_with("!") && ! (text.starts_with("!(") && core.shopts.get("extglob")) {
             ans.not_flag = true;
             ans.text += &text.consume(1);
         }
@@ -102,10 +117,17 @@ impl Pipeline {
             }
         }
 
-        if ans.commands.len() > 0 {
+        if ! ans.commands.is_empty() || ans.time_flag {
             Some(ans)
         }else{
             None
         }
     }
+
+    fn starts_with_word(text: &Feeder, word: &str) -> bool {
+        if ! text.starts_with(word) {
+            return false;
+        }
+        text.len() == word.len() || " \t\n;&|".contains(text.nth(word.len()))
+    }
 }
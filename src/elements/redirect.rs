@@ -8,7 +8,7 @@ use crate::operators::RedirectOp;
 use crate::elements::word::Word;
 use crate::FileDescs;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Redirect {
     pub text: String,
     pub pos: DebugInfo,
@@ -69,9 +69,9 @@ impl Redirect {
         ans.text += &text.consume_blank();
 
         if ans.left_fd == -1 {
-            if ans.redirect_type == RedirectOp::Input {
+            if ans.redirect_type == RedirectOp::Input || ans.redirect_type == RedirectOp::InputAnd {
                 ans.left_fd = 0;
-            }else if ans.redirect_type == RedirectOp::Output {
+            }else if ans.redirect_type == RedirectOp::Output || ans.redirect_type == RedirectOp::OutputAnd {
                 ans.left_fd = 1;
             }
         }
@@ -11,7 +11,7 @@ enum EndStatus{
     NormalEnd,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Script {
     pub jobs: Vec<Job>,
     pub job_ends: Vec<ControlOperator>,
@@ -22,6 +22,10 @@ impl Script {
     pub fn exec(&mut self, core: &mut ShellCore) {
         let mut counter = 0;
         for j in self.jobs.iter_mut() {
+            if core.return_flag || core.discard_flag || core.break_counter > 0 {
+                return;
+            }
+
             if self.job_ends[counter] == ControlOperator::BgAnd {
             //    j.is_bg = true;
                 j.text += " &";
@@ -30,11 +34,6 @@ impl Script {
                 j.exec(core);
             }
             counter += 1;
-
-            if core.return_flag {
-                core.return_flag = false;
-                return;
-            }
         }
     }
 
@@ -58,7 +57,7 @@ impl Script {
 
     fn check_nest(feeder: &mut Feeder, ends: &Vec<&str>, other_ends: &Vec<&str>, empty: bool) -> EndStatus {
         if let Some(end) = ends.iter().find(|e| feeder.starts_with(e)) {
-            if end == &";;" || end == &";&" || end == &";;&" {
+            if end == &";;" || end == &";&" || end == &";;&" || end == &"esac" {
                 return EndStatus::NormalEnd;
             }
             if empty {
@@ -86,7 +85,7 @@ impl Script {
                 "else" => Self::check_nest(feeder, &vec!["fi"], &ends, empty),
                 "while" => Self::check_nest(feeder, &vec!["do"], &ends, empty),
                 "do" => Self::check_nest(feeder, &vec!["done"], &ends, empty),
-                "_)" => Self::check_nest(feeder, &vec![";;", ";&", ";;&"], &ends, empty), // pattern in case
+                "_)" => Self::check_nest(feeder, &vec![";;&", ";;", ";&", "esac"], &ends, empty), // pattern in case
                 _ => EndStatus::NormalEnd,
             };
         }
@@ -110,27 +109,71 @@ impl Script {
         }
     }
 
+    /* errors in sourced files and eval are reported with the line number */
+    fn syntax_error(feeder: &Feeder, core: &mut ShellCore, msg: &str, token: &str) {
+        core.syntax_error = true;
+        match &feeder.origin {
+            Some(o) => eprintln!("{}: line {}: {}", o, feeder.lineno().0, msg),
+            None if ! token.is_empty() => eprintln!("Unexpected token: {}", token),
+            None => {},
+        }
+    }
+
+    /* a parenthesis just after a command like "echo (" */
+    fn check_paren(feeder: &mut Feeder, ans: &Script) -> Option<EndStatus> {
+        if ans.job_ends.last() != Some(&ControlOperator::LeftParen) {
+            return None;
+        }
+
+        let rest = feeder.chars_after(1).collect::<String>();
+        let rest = rest.trim_start_matches([' ', '\t']);
+        let token = match rest.is_empty() || rest.starts_with('\n') {
+            true  => "newline",
+            false => "(",
+        };
+        Some(EndStatus::UnexpectedSymbol(token.to_string()))
+    }
+
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Script> {
         if feeder.len() == 0 {
             return None;
         };
+        if core.nest.is_empty() {
+            core.syntax_error = false;
+        }
     
         let mut ans = Script::new();
         loop{ 
             while Self::eat_job(feeder, core, &mut ans){
                 Self::eat_job_end(feeder, &mut ans);
+                if Self::check_paren(feeder, &ans).is_some() {
+                    break;
+                }
             }
             ans.text += &feeder.consume_blank_return();
 
-            match Self::check_end(feeder, core, ans.jobs.len() == 0) {
+            if core.syntax_error { // already reported in a nested element
+                core.set_var("?", "2");
+                feeder.consume(feeder.len());
+                return None;
+            }
+
+            let status = match Self::check_paren(feeder, &ans) {
+                Some(s) => s,
+                None => Self::check_end(feeder, core, ans.jobs.is_empty()),
+            };
+
+            match status {
                 EndStatus::UnexpectedSymbol(s) => {
-                    eprintln!("Unexpected token: {}", s);
+                    Self::syntax_error(feeder, core, &format!("syntax error near unexpected token `{}'", s), &s);
                     core.set_var("?", "2");
                     feeder.consume(feeder.len());
                     return None;
                 },
                 EndStatus::NeedMoreLine => {
                     if ! feeder.feed_additional_line(core) {
+                        Self::syntax_error(feeder, core, "syntax error: unexpected end of file", "");
+                        core.set_var("?", "2");
                         feeder.consume(feeder.len());
                         return None;
                     }
@@ -4,27 +4,139 @@
 use crate::ShellCore;
 use crate::debuginfo::DebugInfo;
 use crate::Feeder;
+use crate::utils::{eval_glob, quote};
 use crate::elements::value::Value;
+use crate::elements::word::Word;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub enum SubstValue {
+    Scalar(String),
+    Array(Vec<(Option<String>, String)>),
+}
+
+/* evaluated substitution */
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub name: String,
+    pub index: Option<String>,
+    pub value: SubstValue,
+}
+
+impl Assignment {
+    pub fn apply(&self, core: &mut ShellCore) -> bool {
+        match (&self.index, &self.value) {
+            (Some(i), SubstValue::Scalar(v)) => core.set_element(&self.name, i, v),
+            (None, SubstValue::Scalar(v))    => core.set_var(&self.name, v),
+            (_, SubstValue::Array(a))        => core.set_array(&self.name, a.clone()),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let name = match &self.index {
+            Some(i) => format!("{}[{}]", self.name, i),
+            None    => self.name.clone(),
+        };
+
+        match &self.value {
+            SubstValue::Scalar(v) => format!("{}={}", name, quote(v)),
+            SubstValue::Array(a)  => {
+                let elems = a.iter()
+                    .map(|(k, v)| match k {
+                        Some(k) => format!("[{}]={}", k, quote(v)),
+                        None    => quote(v),
+                    })
+                    .collect::<Vec<String>>();
+                format!("{}=({})", name, elems.join(" "))
+            },
+        }
+    }
+
+    /* for arguments of declare, local and readonly: "name=value", "name[i]=value" or "name=(...)" */
+    pub fn from_arg(arg: &str, core: &mut ShellCore) -> Option<Assignment> {
+        let mut feeder = Feeder::new_from(arg.to_string());
+        let pos = feeder.scanner_name();
+        if pos == 0 {
+            return None;
+        }
+        let name = feeder.consume(pos);
+
+        let mut index = None;
+        if feeder.starts_with("[") {
+            let pos = feeder.scanner_until_escape("]");
+            if pos >= feeder.len() {
+                return None;
+            }
+            let i = feeder.consume(pos+1);
+            index = Some(i[1..i.len()-1].to_string());
+        }
+
+        if ! feeder.starts_with("=") {
+            return None;
+        }
+        let value = feeder.consume(feeder.len())[1..].to_string();
+
+        if index.is_none() && value.starts_with("(") && value.ends_with(")") {
+            let mut feeder = Feeder::new_from(arg.to_string());
+            if let Some(mut s) = Substitution::parse(&mut feeder, core) {
+                if feeder.len() == 0 {
+                    return Some(s.eval(core));
+                }
+            }
+        }
+
+        Some(Assignment{name, index, value: SubstValue::Scalar(value)})
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Substitution {
     pub text: String,
     pub name: String,
+    pub index: Option<String>,
     pub value: Value,
+    pub array: Option<Vec<Word>>,
     pub debug: DebugInfo,
 }
 
 impl Substitution {
-    pub fn eval(&mut self, conf: &mut ShellCore) -> Vec<String> { 
+    pub fn eval(&mut self, conf: &mut ShellCore) -> Assignment {
+        let index = self.index.as_ref().map(|i| conf.expand_str(i));
+
+        let value = match &mut self.array {
+            Some(words) => SubstValue::Array(Self::eval_array(words, conf)),
+            None        => {
+                let mut v = "".to_string();
+                for s in self.value.eval(conf){
+                    v += &s;
+                }
+                SubstValue::Scalar(Word::remove_escape(&v))
+            },
+        };
+
+        Assignment{name: self.name.clone(), index, value}
+    }
+
+    fn eval_array(words: &mut [Word], conf: &mut ShellCore) -> Vec<(Option<String>, String)> {
         let mut ans = vec![];
-        ans.push(self.name.clone());
-        
-        let mut v = "".to_string();
-        for s in self.value.eval(conf){
-            v += &s;
-        }
-        ans.push(v);
+        for w in words.iter_mut() {
+            let keyed = w.text.starts_with("[") && w.text.contains("]=");
+            let vals = w.eval(conf);
+
+            if keyed {
+                let s = Word::remove_escape(&vals.join(" "));
+                if let Some((k, v)) = s[1..].split_once("]=") {
+                    ans.push( (Some(k.to_string()), v.to_string()) );
+                }
+                continue;
+            }
 
+            for v in vals {
+                match eval_glob(&v, conf) {
+                    Ok(gs)   => ans.extend(gs.iter().map(|g| (None, Word::remove_escape(g)))),
+                    Err(msg) => eprintln!("{}", msg),
+                }
+            }
+        }
         ans
     }
 
@@ -33,29 +145,74 @@ impl Substitution {
     pub fn new(text: &Feeder, name: String, value: Value) -> Substitution{
         Substitution {
             text: name.clone() + "=" + &value.text.clone(),
-            name: name, 
+            name,
+            index: None,
             value: value,
+            array: None,
             debug: DebugInfo::init(text)
         }
     }
 
+    fn parse_array(text: &mut Feeder, conf: &mut ShellCore, ans: &mut Substitution) -> bool {
+        ans.text += &text.consume(1); // consume of "("
+        let mut words = vec![];
+        loop {
+            ans.text += &text.request_next_line(conf);
+            if text.starts_with(")") {
+                ans.text += &text.consume(1);
+                ans.array = Some(words);
+                return true;
+            }
+
+            match Word::parse(text, conf, false) {
+                Some(w) => {
+                    ans.text += &w.text;
+                    words.push(w);
+                },
+                None => return false,
+            }
+        }
+    }
+
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<Substitution> {
         let backup = text.clone();
         let varname_pos = text.scanner_name();
         let var_part = text.consume(varname_pos);//VarName::new(text, varname_pos);
 
+        let mut index = None;
+        if varname_pos > 0 && text.starts_with("[") {
+            let pos = text.scanner_until_escape("]");
+            if pos < text.len() {
+                index = Some(text.consume(pos+1));
+            }
+        }
+
         if ! text.starts_with("=") {
             text.rewind(backup);
             return None;
         }
-        text.consume(1); // consume of "=" 
- 
-        if let Some(value_part) = Value::parse(text, conf){
-            Some(Substitution::new(text, var_part, value_part))
+        text.consume(1); // consume of "="
+
+        if index.is_none() && text.starts_with("(") {
+            let mut ans = Substitution::new(text, var_part, Value::new());
+            if Self::parse_array(text, conf, &mut ans) {
+                return Some(ans);
+            }
+            text.rewind(backup);
+            return None;
+        }
+
+        let mut ans = if let Some(value_part) = Value::parse(text, conf){
+            Substitution::new(text, var_part, value_part)
         }else{ // empty value
             let empty_word = Value::new();
-            Some(Substitution::new(text, var_part, empty_word))
+            Substitution::new(text, var_part, empty_word)
+        };
+
+        if let Some(i) = index {
+            ans.text = ans.text.replacen("=", &(i.clone() + "="), 1);
+            ans.index = Some(i[1..i.len()-1].to_string());
         }
+        Some(ans)
     }
 }
-
@@ -4,6 +4,7 @@
 pub mod braced;
 pub mod command_substitution;
 pub mod double_quoted;
+pub mod extglob;
 pub mod math_substitution;
 pub mod string_double_quoted;
 pub mod string_non_quoted;
@@ -17,13 +18,26 @@ use self::command_substitution::SubwordCommandSubstitution;
 use self::math_substitution::SubwordMathSubstitution;
 use self::string_non_quoted::SubwordStringNonQuoted;
 use self::double_quoted::SubwordDoubleQuoted;
+use self::extglob::SubwordExtGlob;
 use self::single_quoted::SubwordSingleQuoted;
 use self::braced::SubwordBraced;
 use self::variable::SubwordVariable;
 use std::fmt::Debug;
 use std::fmt;
 
-pub trait Subword {
+pub trait SubwordClone {
+    fn boxed_clone(&self) -> Box<dyn Subword>;
+}
+
+impl<T: 'static + Subword + Clone> SubwordClone for T {
+    fn boxed_clone(&self) -> Box<dyn Subword> { Box::new(self.clone()) }
+}
+
+impl Clone for Box<dyn Subword> {
+    fn clone(&self) -> Box<dyn Subword> { self.boxed_clone() }
+}
+
+pub trait Subword: SubwordClone {
     fn eval(&mut self, _conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<String>>;
     fn get_text(&self) -> String;
     fn permit_lf(&self) -> bool {false}
@@ -46,6 +60,7 @@ pub fn parse_in_arg(text: &mut Feeder, conf: &mut ShellCore, is_in_brace: bool)
     else if let Some(a) = SubwordBraced::parse(text, conf)                        {Some(Box::new(a))}
     else if let Some(a) = SubwordSingleQuoted::parse(text, conf)                  {Some(Box::new(a))}
     else if let Some(a) = SubwordDoubleQuoted::parse(text, conf)                  {Some(Box::new(a))}
+    else if let Some(a) = SubwordExtGlob::parse(text, conf)                       {Some(Box::new(a))}
     else if let Some(a) = SubwordStringNonQuoted::parse(text, is_in_brace, false) {Some(Box::new(a))}
     else {None}
 }
@@ -9,7 +9,7 @@ use crate::elements::subword::Subword;
 use crate::elements::command::Command;
 use crate::elements::command::paren::CommandParen;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordCommandSubstitution {
     pub text: String,
     pub pos: DebugInfo,
@@ -20,11 +20,12 @@ impl Subword for SubwordCommandSubstitution {
     fn eval(&mut self, conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<String>> {
         self.com.substitution = true;
         self.com.exec(conf);
+        let text = self.com.substitution_text.replace("\\", "\\\\"); // backslashes in the output are literal
 
         if ! remove_lf {
-            return vec!(vec!(self.com.substitution_text.clone()));
+            return vec!(vec!(text));
         }
-        let ans = self.com.substitution_text
+        let ans = text
                 .split(" ")
                 .map(|x| x.to_string())
                 .collect::<Vec<String>>();
@@ -11,7 +11,7 @@ use crate::elements::subword::variable::SubwordVariable;
 use crate::elements::subword::command_substitution::SubwordCommandSubstitution;
 use crate::utils::combine;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordDoubleQuoted {
     pub text: String,
     pub pos: DebugInfo,
@@ -36,7 +36,8 @@ impl Subword for SubwordDoubleQuoted {
         for ss in strings {
             let mut anselem = vec![];
             for s in ss {
-                let x = s.replace("*", "\\*");
+                let x = s.replace("*", "\\*").replace("?", "\\?").replace("[", "\\[")
+                         .replace("(", "\\(").replace(")", "\\)").replace("|", "\\|");
                 anselem.push(x);
             }
             ans.push(anselem);
@@ -61,6 +62,7 @@ impl SubwordDoubleQuoted {
             return None;
         };
 
+        let backup = text.clone();
         let mut ans = SubwordDoubleQuoted {
             text: "".to_string(),
             pos: DebugInfo::init(text),
@@ -79,6 +81,9 @@ impl SubwordDoubleQuoted {
             }else if let Some(a) = SubwordStringDoubleQuoted::parse(text, conf) {
                 ans.text += &a.text.clone();
                 ans.subwords.push(Box::new(a));
+            }else if ! text.starts_with("\"") { // unknown expansion or unclosed quote
+                text.rewind(backup);
+                return None;
             }
 
             if text.starts_with("\"") {
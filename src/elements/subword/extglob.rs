@@ -0,0 +1,107 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::debuginfo::DebugInfo;
+use crate::ShellCore;
+use crate::Feeder;
+
+use crate::elements::subword;
+use crate::elements::subword::Subword;
+
+#[derive(Debug, Clone)]
+pub struct SubwordExtGlob {
+    pub text: String,
+    pub prefix: String,
+    pub subwords: Vec<Box<dyn Subword>>,
+    pub pos: DebugInfo,
+}
+
+impl Subword for SubwordExtGlob {
+    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+        let mut ans = self.prefix.clone();
+        for sw in &mut self.subwords {
+            let vs: Vec<String> = sw.eval(conf, false).iter().map(|v| v.join(" ")).collect();
+            ans += &vs.join(" ");
+        }
+
+        vec!(vec!(ans + ")"))
+    }
+
+    fn get_text(&self) -> String {
+        self.text.clone()
+    }
+}
+
+impl SubwordExtGlob {
+    fn literal(text: &mut Feeder, len: usize) -> Box<dyn Subword> {
+        let ch = text.consume(len);
+        let escaped = match ch.as_str() {
+            "|" | "(" | ")" => ch.clone(),
+            _               => "\\".to_owned() + &ch,
+        };
+
+        Box::new(SubwordExtGlobLiteral{ text: ch, escaped })
+    }
+
+    /* parser for ?(..), *(..), +(..), @(..) and !(..) */
+    pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<SubwordExtGlob> {
+        if ! core.shopts.get("extglob") || text.len() < 2 || text.nth(1) != '('
+           || ! "?*+@!".contains(text.nth(0)) {
+            return None;
+        }
+
+        let backup = text.clone();
+        let mut ans = SubwordExtGlob {
+            text: String::new(),
+            prefix: text.consume(2),
+            subwords: vec![],
+            pos: DebugInfo::init(text),
+        };
+        ans.text = ans.prefix.clone();
+
+        let mut depth = 0;
+        loop {
+            if text.len() == 0 && ! text.feed_additional_line(core) {
+                text.rewind(backup);
+                return None;
+            }
+
+            if text.starts_with(")") && depth == 0 {
+                ans.text += &text.consume(1);
+                return Some(ans);
+            }
+
+            let sw = if let Some(a) = SubwordExtGlob::parse(text, core) {
+                Box::new(a)
+            }else if text.starts_with("|") || text.starts_with("(") || text.starts_with(")") {
+                depth += if text.starts_with("(") { 1 }else if text.starts_with(")") { -1 }else{ 0 };
+                SubwordExtGlob::literal(text, 1)
+            }else if let Some(a) = subword::parse_in_arg(text, core, false) {
+                a
+            }else{
+                let len = text.nth(0).len_utf8();
+                SubwordExtGlob::literal(text, len)
+            };
+
+            ans.text += &sw.get_text();
+            ans.subwords.push(sw);
+        }
+    }
+}
+
+/* a character that is not a part of a word, such as a blank or "|" */
+#[derive(Debug, Clone)]
+pub struct SubwordExtGlobLiteral {
+    pub text: String,
+    pub escaped: String,
+}
+
+impl Subword for SubwordExtGlobLiteral {
+    fn eval(&mut self, _: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+        vec!(vec!(self.escaped.clone()))
+    }
+
+    fn get_text(&self) -> String {
+        self.text.clone()
+    }
+}
@@ -9,7 +9,7 @@ use crate::elements::subword::Subword;
 use crate::elements::command::Command;
 use crate::elements::command::double_paren::CommandDoubleParen;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordMathSubstitution {
     pub text: String,
     pub pos: DebugInfo,
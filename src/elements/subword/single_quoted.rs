@@ -8,7 +8,7 @@ use crate::Feeder;
 
 use crate::elements::subword::Subword;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordSingleQuoted {
     pub text: String,
     pub pos: DebugInfo,
@@ -18,7 +18,8 @@ pub struct SubwordSingleQuoted {
 impl Subword for SubwordSingleQuoted {
     fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
         let strip = self.text[1..self.text.len()-1].to_string();
-        let s = strip.replace("\\", "\\\\").replace("*", "\\*"); 
+        let s = strip.replace("\\", "\\\\").replace("*", "\\*").replace("?", "\\?").replace("[", "\\[")
+                     .replace("(", "\\(").replace(")", "\\)").replace("|", "\\|");
         vec!(vec!(s))
     }
 
@@ -8,7 +8,7 @@ use crate::Feeder;
 
 use crate::elements::subword::Subword;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordStringNonQuoted {
     pub text: String,
     pub pos: DebugInfo,
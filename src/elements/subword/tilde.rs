@@ -9,7 +9,7 @@ use crate::utils;
 
 use crate::elements::subword::Subword;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordTildePrefix {
     pub text: String,
     pub pos: DebugInfo,
@@ -20,8 +20,8 @@ impl Subword for SubwordTildePrefix {
         self.text.clone()
     }
 
-    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
-        vec!(vec!(utils::tilde_to_dir(&self.text).0))
+    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+        vec!(vec!(utils::tilde_to_dir(&self.text, &conf.get_var("HOME")).0))
     }
 }
 
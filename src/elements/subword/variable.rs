@@ -8,10 +8,12 @@ use crate::Feeder;
 
 use crate::elements::subword::Subword;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubwordVariable {
     pub text: String,
     pub name: String,
+    pub index: Option<String>,
+    pub length: bool,
     pub empty_option: String,
     pub empty_option_string: String,
     pub pos: DebugInfo,
@@ -19,14 +21,52 @@ pub struct SubwordVariable {
 
 impl Subword for SubwordVariable {
     fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
-        let val = conf.get_var(&self.name);
+        if self.length {
+            return vec!(vec!(self.length(conf).to_string()));
+        }
+
+        let (val, is_set) = match self.index.clone().as_deref() {
+            Some("@") => {
+                let elems = conf.get_elements(&self.name);
+                if ! elems.is_empty() && self.empty_option.is_empty() {
+                    return vec!(elems);
+                }
+                (elems.join(" "), ! elems.is_empty())
+            },
+            Some("*") => {
+                let elems = conf.get_elements(&self.name);
+                let sep = match (conf.in_double_quot, conf.get_var("IFS").chars().next()) {
+                    (true, Some(c)) => c.to_string(),
+                    _               => " ".to_string(),
+                };
+                (elems.join(&sep), ! elems.is_empty())
+            },
+            Some(i) => {
+                let i = conf.expand_str(i);
+                match conf.get_element(&self.name, &i) {
+                    Some(v) => (v, true),
+                    None    => ("".to_string(), false),
+                }
+            },
+            None => {
+                let val = conf.get_var(&self.name);
+                let check_set = ! self.empty_option.is_empty() && ! self.empty_option.starts_with(":");
+                (val, ! check_set || conf.is_set(&self.name))
+            },
+        };
 
-        if val.len() == 0 {
+        let unset = if self.empty_option.starts_with(":") {
+            val.is_empty()
+        }else{
+            ! is_set
+        };
+
+        if unset {
             vec!(vec!(self.empty_treat(conf)))
-        }else if self.empty_option == ":+" {
+        }else if self.empty_option.ends_with("+") {
             vec!(vec!(self.empty_option_string.clone()))
         }else{
-            vec!(vec!(val))
+            vec!(vec!(val.replace("\\", "\\\\")))
         }
     }
 
@@ -39,6 +79,8 @@ impl SubwordVariable {
     pub fn new(text: &mut Feeder) -> SubwordVariable {
         SubwordVariable {
             name: String::new(),
+            index: None,
+            length: false,
             text: String::new(),
             empty_option: String::new(),
             empty_option_string: String::new(),
@@ -46,17 +88,28 @@ impl SubwordVariable {
         }
     }
 
+    fn length(&self, conf: &mut ShellCore) -> usize {
+        match self.index.clone().as_deref() {
+            Some("@") | Some("*") => conf.get_elements(&self.name).len(),
+            Some(i) => {
+                let i = conf.expand_str(i);
+                conf.get_element(&self.name, &i).unwrap_or_default().chars().count()
+            },
+            None => conf.get_var(&self.name).chars().count(),
+        }
+    }
+
     fn empty_treat(&self, conf: &mut ShellCore) -> String {
         let opt: &str = &self.empty_option.clone();
 
-        match opt {
+        match opt.trim_start_matches(':') {
             "" => "".to_string(),
-            ":-" => self.empty_option_string.clone(),
-            ":=" => {
+            "-" => self.empty_option_string.clone(),
+            "=" => {
                 conf.set_var(&self.name, &self.empty_option_string);
                 self.empty_option_string.clone()
             },
-            ":?" => {
+            "?" => {
                 eprintln!("bash: {}: {}",self.name.clone(), self.empty_option_string.clone());
                 conf.set_var("?", "1");
                 "".to_string()
@@ -76,7 +129,10 @@ impl SubwordVariable {
         let mut ans = SubwordVariable::new(text);
         ans.text = text.consume(1);
     
-        let pos = text.scanner_name_or_parameter();
+        let mut pos = text.scanner_name_or_parameter();
+        if pos > 1 && text.nth(0).is_ascii_digit() { // $10 is ${1}0
+            pos = 1;
+        }
         ans.name = text.consume(pos);
         ans.text += &ans.name.clone();
         Some(ans)
@@ -87,14 +143,40 @@ impl SubwordVariable {
         let backup = text.clone();
 
         ans.text = text.consume(2);
+
+        if text.starts_with("#") && ! text.starts_with("#}") {
+            ans.text += &text.consume(1);
+            ans.length = true;
+        }
         
         let pos = text.scanner_name_or_parameter();
         ans.name = text.consume(pos);
         ans.text += &ans.name.clone();
 
-        if text.starts_with( ":-") || text.starts_with( ":=") 
+        if pos > 0 && text.starts_with("[") {
+            let pos = text.scanner_until_escape("]");
+            let index = text.consume(pos);
+            ans.text += &index;
+            ans.index = Some(index[1..].to_string());
+            if ! text.starts_with("]") {
+                text.rewind(backup);
+                return None;
+            }
+            ans.text += &text.consume(1);
+        }
+
+        let len = if text.starts_with( ":-") || text.starts_with( ":=") 
             || text.starts_with( ":?") || text.starts_with( ":+") {
-            ans.empty_option = text.consume(2);
+            2
+        }else if text.starts_with("-") || text.starts_with("=")
+            || text.starts_with("?") || text.starts_with("+") {
+            1
+        }else{
+            0
+        };
+
+        if len > 0 {
+            ans.empty_option = text.consume(len);
             ans.text += &ans.empty_option.clone();
 
             let pos = text.scanner_until_escape("}");
@@ -10,7 +10,7 @@ use crate::elements::subword::Subword;
 use crate::elements::subword::tilde::SubwordTildePrefix;
 use crate::elements::subword::string_non_quoted::SubwordStringNonQuoted;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Word {
     pub text: String,
     pub pos: DebugInfo,
@@ -23,7 +23,7 @@ impl Word {
         let mut ans = "".to_string();
 
         let deescape_twordet = |c: char| {
-            "$*\" \\`{};()^<>?[]'!".chars().any(|x| x == c)
+            "$*\" \\`{};()^<>?[]'!|".chars().any(|x| x == c)
         };
         
         for ch in text.chars() {
@@ -5,6 +5,8 @@ mod scanner;
 mod term;
 
 use std::io;
+use std::collections::VecDeque;
+use nix::unistd::isatty;
 use std::str::Chars;
 use crate::ShellCore;
 //use crate::term;
@@ -17,6 +19,8 @@ pub struct Feeder {
     to_lineno: u32,
     pos_in_line: u32,
     pub feed_stop: bool,
+    lines: Option<VecDeque<String>>, // lines of a file given instead of stdin
+    pub origin: Option<String>, // a file name or "eval" for error messages
 }
 
 impl Feeder {
@@ -27,20 +31,38 @@ impl Feeder {
             to_lineno: 0,
             pos_in_line: 0,
             feed_stop: false,
+            lines: None,
+            origin: None,
         }
     }
 
     pub fn new_from(text: String) -> Feeder {
         let mut ans = Feeder::new();
         ans.feed_stop = true;
+        ans.from_lineno = 1;
+        ans.to_lineno = text.matches('\n').count() as u32 + 1;
         ans.remaining = text;
         ans
     }
 
-    fn read_line_stdin(&self) -> Option<String> {
+    /* the text is fed line by line as a script file */
+    pub fn new_lines(text: &str, origin: &str, lineno: u32) -> Feeder {
+        let mut ans = Feeder::new();
+        ans.lines = Some(text.split_inclusive('\n')
+                         .map(|s| if s.ends_with('\n') { s.to_string() }else{ s.to_string() + "\n" })
+                         .collect());
+        ans.origin = Some(origin.to_string());
+        ans.to_lineno = lineno.saturating_sub(1);
+        ans
+    }
+
+    fn read_line_stdin(&mut self) -> Option<String> {
         if self.feed_stop {
             return None;
         }
+        if let Some(lines) = &mut self.lines {
+            return lines.pop_front();
+        }
         let mut line = String::new();
     
         let len = io::stdin()
@@ -86,7 +108,13 @@ impl Feeder {
 
     pub fn consume(&mut self, cutpos: usize) -> String {
         let cut = self.remaining[0..cutpos].to_string();
-        self.pos_in_line += cutpos as u32;
+        match cut.rfind('\n') {
+            Some(p) => {
+                self.from_lineno += cut.matches('\n').count() as u32;
+                self.pos_in_line = (cutpos - p - 1) as u32;
+            },
+            None => self.pos_in_line += cutpos as u32,
+        }
         self.remaining = self.remaining[cutpos..].to_string();
 
         cut
@@ -146,12 +174,16 @@ impl Feeder {
         self.remaining = self.remaining.replacen(from, to, 1);
     }
 
+    pub fn use_terminal(core: &ShellCore) -> bool {
+        core.has_flag('i') && isatty(0).unwrap_or(false)
+    }
+
     pub fn feed_additional_line(&mut self, core: &mut ShellCore) -> bool {
         if self.feed_stop {
             return false;
         }
 
-        let ret = if core.has_flag('i') {
+        let ret = if self.lines.is_none() && Self::use_terminal(core) {
             let len_prompt = term::prompt_additional();
             if let Some(s) = term::read_line_terminal(len_prompt, core){
                 Some(s)
@@ -179,7 +211,7 @@ impl Feeder {
             return false;
         }
 
-        let line = if core.has_flag('i') {
+        let line = if self.lines.is_none() && Self::use_terminal(core) {
             let len_prompt = term::prompt_normal(core);
             if let Some(ln) = term::read_line_terminal(len_prompt, core) {
                 ln
@@ -19,6 +19,33 @@ impl Feeder {
         pos
     }
     
+    /* a pattern of case, which ends at "|" or ")" out of quotes and parentheses */
+    pub fn scanner_case_pattern(&mut self) -> usize {
+        let mut pos = 0;
+        let mut escaped = false;
+        let mut quote = None;
+        let mut depth = 0;
+        for ch in self.chars_after(0) {
+            if escaped || (ch == '\\' && quote != Some('\'')) {
+                escaped = !escaped;
+            }else if let Some(q) = quote {
+                if ch == q {
+                    quote = None;
+                }
+            }else if ch == '\'' || ch == '"' {
+                quote = Some(ch);
+            }else if ch == '(' {
+                depth += 1;
+            }else if ch == ')' && depth > 0 {
+                depth -= 1;
+            }else if (ch == '|' || ch == ')') && depth == 0 {
+                break;
+            }
+            pos += ch.len_utf8();
+        }
+        pos
+    }
+
     pub fn scanner_until(&mut self, from: usize, to: &str) -> usize {
         let mut pos = from;
         for ch in self.chars_after(from) {
@@ -54,9 +81,11 @@ impl Feeder {
     pub fn scanner_non_quoted_word(&mut self, in_brace: bool, ignore_brace: bool) -> usize {
         let mut escaped = false;
         let mut pos = 0;
+        let mut prev = ' ';
         for ch in self.remaining.chars() {
             if escaped {
                 escaped = false;
+                prev = ' ';
                 pos += ch.len_utf8();
                 continue;
             }
@@ -67,6 +96,13 @@ impl Feeder {
                 continue;
             }
 
+            /* leave an extglob such as @(..) to the next subword */
+            if ch == '(' && "?*+@!".contains(prev) {
+                pos -= 1;
+                break;
+            }
+            prev = ch;
+
             /* stop at meta characters, \n, quotes, start of brace, start of expansion*/
             if let Some(_) = "|&;()<> \t\n\"'$".find(ch) {
                 break;
@@ -122,6 +158,8 @@ impl Feeder {
             return (2, Some(RedirectOp::HereDoc));
         }else if self.starts_with(">&") {
             return (2, Some(RedirectOp::OutputAnd));
+        }else if self.starts_with("<&") {
+            return (2, Some(RedirectOp::InputAnd));
         }else if self.starts_with("&>") {
             return (2, Some(RedirectOp::AndOutput));
         }else if self.starts_with("<>") {
@@ -98,11 +98,10 @@ impl Writer {
         }
     }
 
-    pub fn call_history_from_file(&mut self) -> String {
-        let home = env::var("HOME").expect("HOME is not defined");
+    pub fn call_history_from_file(&mut self, home: &str) -> String {
         let pos = - self.hist_ptr - 1;
 
-        if let Ok(hist_file) = File::open(home + "/.bash_history"){
+        if let Ok(hist_file) = File::open(home.to_string() + "/.bash_history"){
             let mut rev_lines = RevLines::new(BufReader::new(hist_file)).unwrap();
             if let Some(s) = rev_lines.nth(pos as usize) {
                 return s;
@@ -112,12 +111,12 @@ impl Writer {
         "".to_string()
     }
 
-    pub fn call_history(&mut self, inc: i32, history: &Vec<String>){
+    pub fn call_history(&mut self, inc: i32, history: &[String], home: &str){
         self.hist_ptr += inc;
         let len = history.len() as i32;
 
         let h = if self.hist_ptr < 0 {
-            self.call_history_from_file()
+            self.call_history_from_file(home)
         }else if self.hist_ptr < len {
             history[self.hist_ptr as usize].to_string()
         }else{
@@ -239,7 +238,7 @@ impl Writer {
             };
         }else{
             if tab_num == 1 {
-                file_completion(self);
+                file_completion(self, core);
             }else {
                 show_file_candidates(self, core);
                 return;
@@ -345,7 +344,7 @@ pub fn prompt_additional() -> u16 {
 }
 
 pub fn prompt_normal(core: &mut ShellCore) -> u16 {
-    let home = env::var("HOME").unwrap_or("unknown".to_string());
+    let home = core.get_var("HOME");
 
     let path = if let Ok(p) = env::current_dir(){
         p.into_os_string()
@@ -356,8 +355,8 @@ pub fn prompt_normal(core: &mut ShellCore) -> u16 {
         "no_path".to_string()
     };
 
-    let user = env::var("USER").unwrap_or("unknown".to_string());
-    let host = core.vars["HOSTNAME"].clone();
+    let user = core.get_var("USER");
+    let host = core.get_var("HOSTNAME");
 
     print!("\x1b[33m\x1b[1m{}@{}\x1b[m\x1b[m:", user, host);
     print!("\x1b[35m\x1b[1m{}\x1b[m\x1b[m", path);
@@ -386,8 +385,8 @@ pub fn read_line_terminal(left: u16, core: &mut ShellCore) -> Option<String>{
                 writer.end("\r\n");
                 break;
             },
-            event::Key::Up         => writer.call_history(-1, &core.history),
-            event::Key::Down       => writer.call_history(1, &core.history),
+            event::Key::Up         => writer.call_history(-1, &core.history, &core.get_var("HOME")),
+            event::Key::Down       => writer.call_history(1, &core.history, &core.get_var("HOME")),
             event::Key::Left       => writer.move_cursor(-1),
             event::Key::Right      => writer.move_cursor(1),
             event::Key::Backspace  => writer.remove(),
@@ -8,7 +8,8 @@ use std::io::{BufRead, BufReader};
 use std::path::Path;
 
 use crate::ShellCore;
-use crate::utils::{eval_glob, search_commands};
+use crate::utils::search_commands;
+use crate::bash_glob::{expand_path, GlobOptions};
 use crate::utils;
 use crate::feeder::term::Writer;
 use crate::feeder::term;
@@ -100,7 +101,16 @@ fn user_completion_str(input: &String) -> String {
     completed_user[input.len()-1..].to_string()
 }
 
-fn get_completion_str(input: String) -> String {
+fn glob_paths(pattern: &str) -> Vec<String> {
+    let mut ans = expand_path(pattern, &GlobOptions::default());
+    if ans.is_empty() {
+        return vec![pattern.to_string()];
+    }
+    ans.sort();
+    ans
+}
+
+fn get_completion_str(input: String, home: &str) -> String {
     let user_completion = user_completion_str(&input);
     if user_completion.len() != 0 {
         return user_completion + "/";
@@ -108,9 +118,9 @@ fn get_completion_str(input: String) -> String {
 
     let user = input.split("/").nth(0).unwrap().to_string();
     let s_org = input.replace("\\", "") + "*";
-    let (s, home) = utils::tilde_to_dir(&s_org);
+    let (s, home) = utils::tilde_to_dir(&s_org, home);
 
-    let globed_paths = eval_glob(&s.replace("\\", ""));
+    let globed_paths = glob_paths(&s.replace("\\", ""));
     if globed_paths.len() == 0 || globed_paths[0].ends_with("*") {
         return "".to_string();
     };
@@ -139,9 +149,9 @@ fn get_completion_str(input: String) -> String {
     return get_common_string(&cands2);
 }
 
-pub fn file_completion(writer: &mut Writer){
+pub fn file_completion(writer: &mut Writer, core: &ShellCore){
     let arg = writer.last_word();
-    let chars = get_completion_str(arg);
+    let chars = get_completion_str(arg, &core.get_var("HOME"));
     writer.insert_multi(chars.chars());
 }
 
@@ -158,8 +168,8 @@ pub fn show_file_candidates(writer: &mut Writer, core: &mut ShellCore) {
             .collect()
     }else{//other completion
         let s = writer.last_word().replace("\\", "") + "*";
-        let (rs, home) = utils::tilde_to_dir(&s);
-        let globed_paths = eval_glob(&rs);
+        let (rs, home) = utils::tilde_to_dir(&s, &core.get_var("HOME"));
+        let globed_paths = glob_paths(&rs);
 
         let mut globed_paths2 = vec![];
         for p in globed_paths {
@@ -195,7 +205,7 @@ pub fn show_file_candidates(writer: &mut Writer, core: &mut ShellCore) {
 pub fn command_completion(writer: &mut Writer, core: &ShellCore){
     let s = writer.chars.iter().collect::<String>();
 
-    let mut paths = search_commands(&(s.clone() + &"*"));
+    let mut paths = search_commands(&(s.clone() + "*"), &core.get_var("PATH"));
     paths.append(&mut utils::search_aliases(&s, core));
     paths.append(&mut utils::search_builtin(&s, core));
 
@@ -220,7 +230,7 @@ pub fn command_completion(writer: &mut Writer, core: &ShellCore){
 pub fn show_command_candidates(writer: &mut Writer, core: &mut ShellCore) {
     let s = writer.chars.iter().collect::<String>();
 
-    let mut paths = search_commands(&(s.clone() + &"*"));
+    let mut paths = search_commands(&(s.clone() + "*"), &core.get_var("PATH"));
     paths.append(&mut utils::search_aliases(&s, core));
     paths.append(&mut utils::search_builtin(&s, core));
 
@@ -244,13 +254,14 @@ pub fn show_command_candidates(writer: &mut Writer, core: &mut ShellCore) {
 
 #[test]
 fn file_candidates() {
-    let comp_str = get_completion_str("/etc/passw".to_string());
+    let home = std::env::var("HOME").unwrap_or_default();
+    let comp_str = get_completion_str("/etc/passw".to_string(), &home);
     assert_eq!(comp_str, "d");
 
-    let comp_str = get_completion_str("/li".to_string());
+    let comp_str = get_completion_str("/li".to_string(), &home);
     assert_eq!(comp_str, "b");
 
-    let comp_str = get_completion_str("~roo".to_string());
+    let comp_str = get_completion_str("~roo".to_string(), &home);
     assert_eq!(comp_str, "t/");
 }
 
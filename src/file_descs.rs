@@ -9,7 +9,7 @@ use std::fs::OpenOptions;
 use std::os::unix::io::IntoRawFd;
 use crate::ShellCore;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FileDescs {
     pub redirects: Vec<Box<Redirect>>,
     pub pipein: RawFd,
@@ -69,9 +69,12 @@ impl FileDescs {
             }else{
                 panic!("Cannot open the file: {}", path);
             };
-        }else if r.redirect_type == RedirectOp::OutputAnd  {
+        }else if r.redirect_type == RedirectOp::OutputAnd || r.redirect_type == RedirectOp::InputAnd {
             if let Ok(n) = path.parse::<i32>() {
-                dup2(n, r.left_fd).expect("Invalid fd");
+                if dup2(n, r.left_fd).is_err() {
+                    conf.set_var("?", "1");
+                    return Err(format!("bash: {}: Bad file descriptor", n));
+                }
             }else{
                 conf.set_var("?", "1");
                 if let Some(a) = &r.right_word {
@@ -12,35 +12,146 @@ mod utils;
 mod feeder;
 mod debuginfo;
 
-use std::{env, process, path};
-use std::os::linux::fs::MetadataExt;
+use std::{env, process};
+use nix::unistd::{isatty, getppid, getuid, geteuid};
 use std::fs::{File,OpenOptions};
 use std::io::Read;
 
 use crate::core::ShellCore;
 use crate::core::proc;
+use crate::core::builtins;
+use crate::core::variables::Scope;
 use crate::feeder::Feeder;
 
 use crate::elements::script::Script;
+use crate::elements::word::Word;
 
 use crate::file_descs::FileDescs;
 use std::os::unix::io::IntoRawFd;
 
-fn is_interactive(pid: u32) -> bool {
-    let std_path = format!("/proc/{}/fd/0", pid);
-    match path::Path::new(&std_path).metadata() {
-        Ok(metadata) => metadata.st_mode() == 8592, 
-        Err(err) => panic!("{}", err),
+fn is_interactive(inv: &Invocation) -> bool {
+    if inv.interactive {
+        return true;
     }
+    if inv.command.is_some() || inv.script.is_some() {
+        return false;
+    }
+
+    isatty(0).unwrap_or(false) && isatty(2).unwrap_or(false)
 }
 
-fn read_bashrc(core: &mut ShellCore){
-    let home = env::var("HOME").expect("HOME is not defined");
-    if let Ok(_) = File::open(home.clone() + "/.rusty_bashrc") {
+fn set_interactive_flags(core: &mut ShellCore, inv: &Invocation) {
+    if is_interactive(inv) {
+        core.flags += "i";
+        if ! inv.flags_off.contains('m') {
+            core.set_flag('m', true);
+        }
+        if ! core.is_set("PS1") {
+            core.set_var("PS1", "\\u@\\h:\\w\\$ ");
+        }
+        if ! core.is_set("PS2") {
+            core.set_var("PS2", "> ");
+        }
+    }else{
+        for v in ["PS1", "PS2"] {
+            core.vars.remove(v);
+        }
+    }
+
+    if inv.command.is_none() && inv.script.is_none() {
+        core.flags += "s";
+    }
+}
+
+fn source_file(core: &mut ShellCore, file: &str) -> bool {
+    if File::open(file).is_ok() {
         let f = core.builtins["source"];
-        let mut words = vec!("source".to_string(), home.clone() + "/.rusty_bashrc");
+        let mut words = vec!("source".to_string(), file.to_string());
         f(core, &mut words);
+        return true;
+    }
+    false
+}
+
+fn read_profile(core: &mut ShellCore, inv: &Invocation){
+    if inv.noprofile {
+        return;
+    }
+
+    source_file(core, "/etc/profile");
+
+    let home = core.get_var("HOME");
+    for f in [ "/.bash_profile", "/.bash_login", "/.profile" ] {
+        if source_file(core, &(home.clone() + f)) {
+            return;
+        }
+    }
+}
+
+fn read_bashrc(core: &mut ShellCore, inv: &Invocation){
+    if inv.norc {
+        return;
     }
+
+    let file = match &inv.rcfile {
+        Some(f) => f.clone(),
+        None    => core.get_var("HOME") + "/.rusty_bashrc",
+    };
+    source_file(core, &file);
+}
+
+fn read_bash_env(core: &mut ShellCore){
+    let bash_env = core.get_var("BASH_ENV");
+    if bash_env.is_empty() {
+        return;
+    }
+
+    let mut feeder = Feeder::new_from(bash_env.clone());
+    let file = match Word::parse(&mut feeder, core, false) {
+        Some(mut w) => Word::remove_escape(&w.eval(core).join(" ")),
+        None        => bash_env,
+    };
+    source_file(core, &file);
+}
+
+fn read_startup_files(core: &mut ShellCore, inv: &Invocation){
+    if core.shopts.get("login_shell") {
+        read_profile(core, inv);
+    }else if core.has_flag('i') {
+        read_bashrc(core, inv);
+    }else{
+        read_bash_env(core);
+    }
+}
+
+fn set_shell_vars(core: &mut ShellCore) {
+    let readonly = [("PPID", getppid().to_string()), ("UID", getuid().to_string()),
+                    ("EUID", geteuid().to_string())];
+    for (name, value) in readonly {
+        core.set_var(name, &value);
+        core.vars.entry(name, &Scope::Global).set_attr('r', true);
+    }
+
+    let shlvl = core.get_var("SHLVL").parse::<i32>().unwrap_or(0) + 1;
+    core.set_var("SHLVL", &shlvl.to_string());
+    core.vars.entry("SHLVL", &Scope::Global).set_attr('x', true);
+
+    if let Ok(dir) = env::current_dir() {
+        let dir = dir.display().to_string();
+        if core.get_var("PWD") != dir {
+            core.set_var("PWD", &dir);
+        }
+    }
+
+    const V: &str = env!("CARGO_PKG_VERSION");
+    core.set_var("BASH_VERSION", &format!("{}(1)-release", V));
+    let mut versinfo: Vec<(Option<String>, String)> = V.split('.')
+        .map(|n| (None, n.to_string())).collect();
+    versinfo.push( (None, "1".to_string()) );
+    versinfo.push( (None, "release".to_string()) );
+    versinfo.push( (None, format!("{}-{}", env::consts::ARCH, env::consts::OS)) );
+    core.set_array("BASH_VERSINFO", versinfo);
+    core.vars.entry("BASH_VERSINFO", &Scope::Global).set_attr('r', true);
 }
 
 fn get_hostname() -> String{
@@ -55,7 +166,7 @@ fn get_hostname() -> String{
     "unknown".to_string()
 }
 
-fn show_version() {
+fn show_version() -> ! {
     const V: &'static str = env!("CARGO_PKG_VERSION");
     eprintln!("Rusty Bash, Version {}", V);
     eprintln!("© 2022 Ryuichi Ueda");
@@ -66,28 +177,129 @@ fn show_version() {
     process::exit(0);
 }
 
-fn has_option(word: &String, opt: String) -> bool {
-    if word.len() < 2 {
-        return false;
+#[derive(Default)]
+struct Invocation {
+    command: Option<String>,
+    script: Option<String>,
+    args: Vec<String>,
+    flags: String,
+    flags_off: String,
+    read_stdin: bool,
+    interactive: bool,
+    login: bool,
+    norc: bool,
+    noprofile: bool,
+    rcfile: Option<String>,
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("bash: {}", msg);
+    eprintln!("Usage:\trusty_bash [long option] [option] ...");
+    eprintln!("\trusty_bash [long option] [option] script-file ...");
+    process::exit(2);
+}
+
+fn parse_long_option(words: &[String], pos: &mut usize, inv: &mut Invocation) {
+    match words[*pos].as_ref() {
+        "--version"    => show_version(),
+        "--norc"       => inv.norc = true,
+        "--noprofile"  => inv.noprofile = true,
+        "--login"      => inv.login = true,
+        "--rcfile" | "--init-file" => {
+            *pos += 1;
+            if *pos >= words.len() {
+                usage_error(&format!("{}: option requires an argument", words[*pos-1]));
+            }
+            inv.rcfile = Some(words[*pos].clone());
+        },
+        opt => usage_error(&format!("{}: invalid option", opt)),
     }
+}
+
+fn parse_short_options(words: &[String], pos: &mut usize, inv: &mut Invocation) {
+    let word = words[*pos].clone();
+    let on = word.starts_with("-");
 
-    if word.chars().nth(0) == Some('-') && word.chars().nth(1) == Some('-') { // --option
-        return word[2..] == opt;
+    for ch in word[1..].chars() {
+        match ch {
+            'c' if on => inv.command = Some(String::new()),
+            's' if on => inv.read_stdin = true,
+            'i' if on => inv.interactive = true,
+            'l' if on => inv.login = true,
+            'o' => {
+                *pos += 1;
+                if *pos >= words.len() {
+                    usage_error(&format!("{}o: option requires an argument", &word[0..1]));
+                }
+                match builtins::option_to_flag(&words[*pos]) {
+                    Some(f) => set_flag(&mut inv.flags, &mut inv.flags_off, f, on),
+                    None    => usage_error(&format!("{}: invalid option name", words[*pos])),
+                }
+            },
+            'a' | 'b' | 'd' | 'e' | 'f' | 'h' | 'k' | 'm' | 'n' | 'p' | 't'
+            | 'u' | 'v' | 'x' | 'B' | 'C' | 'E' | 'H' | 'P' | 'T' => set_flag(&mut inv.flags, &mut inv.flags_off, ch, on),
+            _ => usage_error(&format!("{}{}: invalid option", &word[0..1], ch)),
+        }
     }
+}
 
-    if word.chars().nth(0) == Some('-') { // -options
-        return word.chars().any(|c| c.to_string() == opt);
+fn set_flag(flags: &mut String, flags_off: &mut String, flag: char, on: bool) {
+    *flags = flags.replace(flag, "");
+    *flags_off = flags_off.replace(flag, "");
+    if on {
+        flags.push(flag);
+    }else{
+        flags_off.push(flag);
     }
+}
 
+fn parse_args(words: &[String]) -> Invocation {
+    let mut inv = Invocation::default();
 
-    false
+    let mut pos = 1;
+    while pos < words.len() {
+        let w = &words[pos];
+        if w == "--" || w == "-" {
+            pos += 1;
+            break;
+        }else if w.starts_with("--") {
+            parse_long_option(words, &mut pos, &mut inv);
+        }else if w.len() > 1 && (w.starts_with("-") || w.starts_with("+")) {
+            parse_short_options(words, &mut pos, &mut inv);
+        }else{
+            break;
+        }
+        pos += 1;
+    }
+
+    if inv.command.is_some() {
+        if pos >= words.len() {
+            usage_error("-c: option requires an argument");
+        }
+        inv.command = Some(words[pos].clone());
+        pos += 1;
+    }else if ! inv.read_stdin && pos < words.len() {
+        inv.script = Some(words[pos].clone());
+        pos += 1;
+    }
+
+    /* $0 is the name after -c string, the script file, or the shell itself */
+    let arg0 = if inv.command.is_some() && pos < words.len() {
+        pos += 1;
+        words[pos-1].clone()
+    }else if let Some(s) = &inv.script {
+        s.clone()
+    }else{
+        words[0].clone()
+    };
+
+    inv.args.push(arg0);
+    inv.args.extend(words[pos..].iter().cloned());
+    inv
 }
 
 fn main() {
     let words: Vec<String> = env::args().collect();
-    if words.len() > 1 && words[1] == "--version" {
-        show_version();
-    }
 
     /* Ignore signals */
     proc::ignore_signals();
@@ -98,64 +310,85 @@ fn main() {
     unsafe { signal::signal(Signal::SIGTSTP, SigHandler::SigIgn) }.unwrap();
     */
 
+    let inv = parse_args(&words);
     let mut core = ShellCore::new();
-    for word in &words {
-        core.args.push(word.clone());
+    core.args = inv.args.clone();
+    core.flags = inv.flags.clone();
+    if inv.login || words[0].starts_with("-") {
+        core.shopts.set(&"login_shell".to_string(), true);
     }
 
-    if words.len() > 1 {
-        if let Ok(file) = OpenOptions::new().read(true).open(&words[1]){
-            FileDescs::dup_and_close(file.into_raw_fd(), 0);
-        }
-    }
-
-    for f in [ "d", "v", "x" ] {
-        if words.iter().any(|a| has_option(a, f.to_string())) {
-            core.flags += f;
+    core.call_stack[0].source = inv.script.clone().unwrap_or_default();
+    core.call_stack[0].args = inv.args.iter().skip(1).cloned().collect();
+    core.set_call_vars();
+    if let Some(script) = &inv.script {
+        match OpenOptions::new().read(true).open(script) {
+            Ok(file) => FileDescs::dup_and_close(file.into_raw_fd(), 0),
+            Err(_) => {
+                eprintln!("bash: {}: No such file or directory", script);
+                process::exit(127);
+            },
         }
     }
 
     let pid = process::id();
     core.set_var("$", &pid.to_string());
     core.set_var("IFS", " \t\n");
+    core.set_var("PS4", "+ ");
     core.set_var("HOSTNAME", &get_hostname());
     core.set_var("SHELL", "rustybash");
-    core.set_var("BASH", &core.args[0].to_string());
-    if is_interactive(pid) {
-        core.flags += "i";
-    }
+    core.set_var("BASH", &words[0]);
+    set_shell_vars(&mut core);
+    set_interactive_flags(&mut core, &inv);
+
+    read_startup_files(&mut core, &inv);
 
-    read_bashrc(&mut core);
+    if let Some(command) = &inv.command {
+        let mut feeder = Feeder::new_lines(command, "bash: -c", 1);
+        while feeder.feed_line(&mut core) {
+            exec_feeder(&mut feeder, &mut core);
+        }
+        exit_shell(&mut core);
+    }
     main_loop(&mut core);
 }
 
+fn exec_feeder(feeder: &mut Feeder, core: &mut ShellCore) {
+    while let Some(mut e) = Script::parse(feeder, core){
+//            eprintln!("{:?}", &e);
+        if feeder.len() != 0 && feeder.nth(0) == ')' {
+            feeder.consume(feeder.len());
+            eprintln!("Unknown phrase");
+            core.set_var("?", "2");
+            break;
+        }
+        e.exec(core);
+        core.discard_flag = false;
+    }
+}
+
 fn main_loop(core: &mut ShellCore) {
     let mut feeder = Feeder::new();
     loop {
         if !feeder.feed_line(core) {
-            if core.has_flag('i') {
+            if Feeder::use_terminal(core) { // Ctrl+C
                 continue;
             }else{
                 break;
             }
         }
-        while let Some(mut e) = Script::parse(&mut feeder, core){
-//            eprintln!("{:?}", &e);
-            if feeder.len() != 0 && feeder.nth(0) == ')' {
-                feeder.consume(feeder.len());
-                eprintln!("Unknown phrase");
-                core.set_var("?", "2");
-                break;
-            }
-            e.exec(core);
-        }
+        exec_feeder(&mut feeder, core);
         core.check_jobs();
     }
 
+    exit_shell(core);
+}
+
+fn exit_shell(core: &mut ShellCore) -> ! {
     if let Ok(status) = core.get_var("?").to_string().parse::<i32>(){
-        process::exit(status);
+        core.exit(status);
     }else{
         eprintln!("Shell internal error");
-        process::exit(1);
+        core.exit(1);
     }
 }
@@ -18,13 +18,14 @@ pub enum ControlOperator {
     NoChar,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum RedirectOp {
     Output, /* > */ 
     Input, /* < */
     InOut, /* <> */
     AndOutput, /* &> */ 
     OutputAnd, /* >& */ 
+    InputAnd, /* <& */ 
     Append, /* >> */ 
     HereDoc, /* << */ 
     AndAppend, /* &>> */ 
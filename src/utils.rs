@@ -1,85 +1,80 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use glob::glob;
-use crate::env;
+use crate::bash_glob::{collate, expand_path, glob_match_path, has_glob_chars, GlobOptions};
 use std::fs;
 use std::path::Path;
 use std::io::{BufRead, BufReader};
 use std::fs::OpenOptions;
 use crate::ShellCore;
+use crate::elements::word::Word;
+use std::time::Duration;
 
 pub fn chars_to_string(chars: &Vec<char>) -> String {
     chars.iter().collect::<String>()
 }
 
-fn is_glob(s: &String) -> bool {
-    let mut escaped = false;
+pub fn quote(s: &str) -> String {
+    let safe = |c: char| c.is_alphanumeric() || "_-./:=@%+,".contains(c);
+    if ! s.is_empty() && s.chars().all(safe) {
+        return s.to_string();
+    }
 
-    for ch in s.chars() {
-        if escaped {
-            continue;
-        }else if ! escaped && ch == '\\' {
-            escaped = true;
-            continue;
-        }
+    format!("'{}'", s.replace("'", "'\\''"))
+}
 
-        if ch == '*' || ch == '[' || ch == '?' {
-            return true;
+pub fn dquote(s: &str) -> String {
+    let mut ans = "\"".to_string();
+    for c in s.chars() {
+        if "\\\"$`".contains(c) {
+            ans.push('\\');
         }
+        ans.push(c);
     }
-    return false;
+    ans + "\""
 }
 
-pub fn eval_glob(globstr: &String) -> Vec<String> {
-    if ! is_glob(&globstr) {
-        return vec!(globstr.clone());
+pub fn eval_glob(globstr: &String, core: &ShellCore) -> Result<Vec<String>, String> {
+    if ! has_glob_chars(globstr) {
+        return Ok(vec!(globstr.clone()));
     }
 
-    let mut ans = vec![];
-    let g = globstr.replace("\\ ", " ").to_string();
-
-    //TODO: too ugly
-    if let Ok(path) = glob(&g) {
-        for dir in path {
-            if let Ok(d) = dir {
-                if let Some(s) = d.to_str() {
-                    if let Some('/') = g.chars().last() {
-                        // the library omits the last / 
-                        ans.push(s.to_string() + "/");
-                    }else{
-                        ans.push(s.to_string());
-                    }
-                };
-            };
-        };
-    };
+    let ignore = core.get_var("GLOBIGNORE");
+    let mut opts = GlobOptions::new(&core.shopts);
+    opts.dotglob |= ! ignore.is_empty();
+
+    let mut ans = expand_path(globstr, &opts);
+    if ! ignore.is_empty() {
+        let pats: Vec<String> = ignore.split(':').map(|p| p.to_string()).collect();
+        let opts = GlobOptions { extglob: opts.extglob, ..Default::default() };
+        ans.retain(|a| ! pats.iter().any(|p| glob_match_path(p, a, &opts)));
+    }
 
     if ans.len() == 0 {
-        return vec!(globstr.clone());
+        if core.shopts.get("failglob") {
+            return Err(format!("bash: no match: {}", Word::remove_escape(globstr)));
+        }else if core.shopts.get("nullglob") {
+            return Ok(vec![]);
+        }
+        return Ok(vec!(globstr.clone()));
     }
 
-    ans
-}
+    let locale = ["LC_ALL", "LC_COLLATE", "LANG"].iter()
+        .map(|v| core.get_var(v))
+        .find(|v| ! v.is_empty())
+        .unwrap_or_default();
+    ans.sort_by(|a, b| collate(a, b, &locale));
 
-pub fn search_commands(globstr: &String) -> Vec<String> {
-    let dirs = if let Ok(p) = env::var("PATH") {
-        p.split(':').map(|s| s.to_string()).collect()
-    }else{
-        vec![]
-    };
+    /* file names are given in the escaped form as the other parts of words */
+    Ok(ans.iter().map(|p| p.replace("\\", "\\\\")).collect())
+}
 
+pub fn search_commands(globstr: &str, path: &str) -> Vec<String> {
     let mut ans: Vec<String> = vec![];
-    for d in dirs {
-        if let Ok(path) = glob(&(d + "/" + globstr)) {
-            for dir in path {
-                if let Ok(d) = dir {
-                    if let Some(s) = d.to_str() {
-                        ans.push(s.to_string());
-                    };
-                };
-            };
-        };
+    for d in path.split(':').filter(|d| ! d.is_empty()).map(|d| d.to_string()) {
+        let mut paths = expand_path(&(d + "/" + globstr), &GlobOptions::default());
+        paths.sort();
+        ans.append(&mut paths);
     };
 
     ans
@@ -159,19 +154,19 @@ pub fn blue_string(s: &String) -> String {
     format!("\x1b[34m{}\x1b[m", s)
 }
 
-pub fn tilde_to_dir(given_path: &String) -> (String, Option<String>){
+pub fn tilde_to_dir(given_path: &str, home: &str) -> (String, Option<String>){
     if ! given_path.starts_with("~"){
-        return (given_path.clone(), None);
+        return (given_path.to_string(), None);
     }
 
     let user = given_path[1..].split("/").nth(0).unwrap().to_string();
 
     let home_path = if user.len() == 0 {
-        env::var("HOME").expect("Home is not set")
+        home.to_string()
     }else if let Some(h) = get_home(user.clone()) {
         h
     }else{
-        return (given_path.clone(), None)
+        return (given_path.to_string(), None)
     };
 
     let replaced_path = given_path.replacen(&("~".to_owned() + &user), &home_path, 1);
@@ -237,7 +232,7 @@ fn get_column_num(list: &Vec<String>, width: u32) -> (usize, Vec<u32>) {
             n += line_num;
         }
 
-        if wid < width {
+        if wid < width || colnum == 1 {
             ans = colnum;
             colwids_ans = colwids;
         }else{
@@ -273,18 +268,12 @@ pub fn align_elems_on_term(list: &Vec<String>, width: u32) -> String {
 }
 
 
-pub fn get_fullpath(com: &String) -> String {
+pub fn get_fullpath(com: &String, path: &str) -> String {
     if Path::is_file(Path::new(&com)) {
         return com.to_string();
     }
-   
-    let dirs = if let Ok(p) = env::var("PATH") {
-        p.split(':').map(|s| s.to_string()).collect()
-    }else{
-        vec![]
-    };
 
-    for d in dirs {
+    for d in path.split(':').filter(|d| ! d.is_empty()).map(|d| d.to_string()) {
         let path = d + "/" + com;
         if fs::metadata(&path).is_ok() {
             return path;
@@ -293,3 +282,70 @@ pub fn get_fullpath(com: &String) -> String {
 
     "".to_string()
 }
+
+fn format_duration(t: Duration, precision: u32, long: bool) -> String {
+    let micros = t.as_micros();
+    let secs = if long { micros / 1_000_000 % 60 }else{ micros / 1_000_000 };
+    let frac = micros % 1_000_000 / 10u128.pow(6 - precision);
+
+    let num = match precision {
+        0 => secs.to_string(),
+        _ => format!("{}.{:0width$}", secs, frac, width=precision as usize),
+    };
+
+    match long {
+        true  => format!("{}m{}s", micros / 60_000_000, num),
+        false => num,
+    }
+}
+
+/* expands %[p][l]R, %[p][l]U, %[p][l]S and %P in TIMEFORMAT; returns the invalid character on error */
+pub fn format_times(format: &str, real: Duration, user: Duration, sys: Duration) -> Result<String, char> {
+    let mut ans = String::new();
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            ans.push(c);
+            continue;
+        }
+
+        let mut spec = match chars.next() {
+            Some(s) => s,
+            None    => {
+                ans.push('%');
+                break;
+            },
+        };
+
+        if spec == '%' {
+            ans.push('%');
+            continue;
+        }
+        if spec == 'P' {
+            let cpu = (user + sys).as_secs_f64();
+            let real = real.as_secs_f64();
+            ans += &format!("{:.2}", if real > 0.0 { cpu * 100.0 / real }else{ 0.0 });
+            continue;
+        }
+
+        let mut precision = 3;
+        if let Some(d) = spec.to_digit(10) {
+            precision = std::cmp::min(d, 3);
+            spec = chars.next().unwrap_or(' ');
+        }
+        let long = spec == 'l';
+        if long {
+            spec = chars.next().unwrap_or(' ');
+        }
+
+        match spec {
+            'R' => ans += &format_duration(real, precision, long),
+            'U' => ans += &format_duration(user, precision, long),
+            'S' => ans += &format_duration(sys, precision, long),
+            c   => return Err(c),
+        }
+    }
+
+    Ok(ans)
+}
@@ -37,6 +37,92 @@ res=$($com <<< 'HOGE=A;export HOGE;printenv HOGE')
 res=$($com <<< 'export HOGE=A;printenv HOGE')
 [ "$res" = "A" ] || err $LINENO
 
+# declare, local, readonly
+
+res=$($com <<< 'A=g; f () { local A=l ; g ; } ; g () { echo $A ; } ; f ; echo $A')
+[ "$res" = "l
+g" ] || err $LINENO
+
+res=$($com <<< 'f () { declare A=l ; } ; f ; echo $A')
+[ "$res" = "" ] || err $LINENO
+
+res=$($com <<< 'f () { declare -g A=g ; } ; f ; echo $A')
+[ "$res" = "g" ] || err $LINENO
+
+res=$($com <<< 'f () { local - ; set -x ; } ; f ; echo $-')
+[ "$res" = "s" ] || err $LINENO
+
+res=$($com <<< 'local A=1')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'x=g ; f () { local x=1 ; unset x ; echo "[$x]" ; } ; f ; echo $x')
+[ "$res" = "[]
+g" ] || err $LINENO
+
+res=$($com <<< 'readonly r=1 ; f () { local r=3 ; echo $? $r ; } ; f' 2>&1)
+[ "$res" = "bash: local: r: readonly variable
+1 1" ] || err $LINENO
+
+res=$($com <<< 'readonly A=1 ; A=2 ; echo $? $A')
+[ "$res" = "1 1" ] || err $LINENO
+
+res=$($com <<< 'declare -r A=1 ; unset A ; echo $A')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'A=(a "b c" d) ; echo ${A[1]} ${#A[@]}')
+[ "$res" = "b c 3" ] || err $LINENO
+
+res=$($com <<< 'declare -a A=(a "b c") ; A[3]=x ; declare -p A')
+[ "$res" = 'declare -a A=([0]="a" [1]="b c" [3]="x")' ] || err $LINENO
+
+res=$($com <<< 'declare -A A=([k]=v) ; echo ${A[k]}')
+[ "$res" = "v" ] || err $LINENO
+
+res=$($com <<< 'declare -ix A=1 ; declare -p A')
+[ "$res" = 'declare -ix A="1"' ] || err $LINENO
+
+res=$($com <<< "A='a\"b\$c' ; eval \"\$(declare -p A)\" ; echo \"\$A\"")
+[ "$res" = 'a"b$c' ] || err $LINENO
+
+res=$($com <<< 'A=(a "b c") ; eval "$(declare -p A)" ; echo ${A[1]}')
+[ "$res" = "b c" ] || err $LINENO
+
+res=$($com <<< 'f () { : ; } ; declare -F')
+[ "$res" = "declare -f f" ] || err $LINENO
+
+res=$($com <<< 'declare -i A=1+2 ; A=A*3 ; echo $A')
+[ "$res" = "9" ] || err $LINENO
+
+res=$($com <<< 'declare -i c=5 ; c=1/0 ; echo $? "[$c]"' 2>/dev/null)
+[ "$res" = "1 [5]" ] || err $LINENO
+
+res=$($com <<< 'echo "4*5" | (declare -i A; read A; echo $A)')
+[ "$res" = "20" ] || err $LINENO
+
+res=$($com <<< 'declare -l A=ABC ; declare -u B=abc ; echo $A $B')
+[ "$res" = "abc ABC" ] || err $LINENO
+
+res=$($com <<< 'A=1 ; declare -n R=A ; R=2 ; echo $A $R')
+[ "$res" = "2 2" ] || err $LINENO
+
+res=$($com <<< 'A=(1 2 3) ; declare -n R=A[1] ; R=x ; echo ${A[@]}')
+[ "$res" = "1 x 3" ] || err $LINENO
+
+res=$($com <<< 'f () { local -n R=$1 ; R=in ; } ; A=out ; f A ; echo $A')
+[ "$res" = "in" ] || err $LINENO
+
+res=$($com <<< 'A=1 ; declare -n R=A ; unset R ; echo ${A-unset} ; unset -n R ; echo ${R-unset}')
+[ "$res" = "unset
+unset" ] || err $LINENO
+
+res=$($com <<< 'declare -n R=R')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'declare -n a=b ; declare -n b=a ; a=1 ; echo $? "[$a]" ; declare -p a b' 2>/dev/null)
+[ "$res" = "1 []
+declare -n a=\"b\"
+declare -n b=\"a\"" ] || err $LINENO
+
 # eval
 
 res=$($com <<< 'eval echo hello')
@@ -58,5 +144,200 @@ res=$($com <<< 'eval "echo $(echo a b c);" "echo $(echo a b c)"' )
 [ "$res" = "a b c
 a b c" ] || err $LINENO
 
+res=$($com <<< 'eval "echo )" ; echo $?' 2>&1)
+[ "$res" = "bash: eval: line 1: syntax error near unexpected token \`)'
+2" ] || err $LINENO
+
+res=$($com <<< 'eval "if true" ; echo $?' 2>&1)
+[ "$res" = "bash: eval: line 2: syntax error: unexpected end of file
+2" ] || err $LINENO
+
+res=$($com <<< 'eval "echo a; if" ; echo $?' 2>&1)
+[ "$res" = "bash: eval: line 2: syntax error: unexpected end of file
+2" ] || err $LINENO
+
+res=$($com <<< 'eval "echo (" ; echo $?' 2>&1)
+[ "$res" = "bash: eval: line 1: syntax error near unexpected token \`newline'
+2" ] || err $LINENO
+
+res=$($com <<< 'eval "if true
+then echo multi
+fi"')
+[ "$res" = "multi" ] || err $LINENO
+
+res=$($com <<< 'echo
+eval "echo \$LINENO
+echo \$LINENO"')
+[ "$res" = "
+2
+3" ] || err $LINENO
+
+res=$($com <<< 'f(){ eval "return 3" ; echo no ; } ; f ; echo $?')
+[ "$res" = "3" ] || err $LINENO
+
+res=$($com <<< 'f(){ eval "local A=1" ; echo $A ; } ; f ; echo ${A-unset}')
+[ "$res" = "1
+unset" ] || err $LINENO
+
+# export
+
+res=$($com <<< 'A=1 ; export A ; printenv A')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'export A=2 ; export -n A ; printenv A ; echo $A')
+[ "$res" = "2" ] || err $LINENO
+
+res=$($com <<< 'export B=3 ; export -p | grep " B="')
+[ "$res" = 'declare -x B="3"' ] || err $LINENO
+
+res=$($com <<< 'A=x printenv A ; echo ${A-unset}')
+[ "$res" = "x
+unset" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo $A; } ; A=t f ; echo ${A-unset}')
+[ "$res" = "t
+unset" ] || err $LINENO
+
+res=$($com <<< 'PATH=/nonexist ; ls')
+[ "$?" = "127" ] || err $LINENO
+
+res=$($com <<< 'export -f nofunc')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'export -z')
+[ "$?" = "2" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo hi $1; } ; export -f f ; bash -c "f a"')
+[ "$res" = "hi a" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo hi $1; } ; export -f f ; seq 2 | xargs -n1 ../target/debug/rusty_bash -c "f \$0"')
+[ "$res" = "hi 1
+hi 2" ] || err $LINENO
+
+res=$(bash -c 'g(){ echo from bash $1; } ; export -f g ; ../target/debug/rusty_bash -c "g b"')
+[ "$res" = "from bash b" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo hi; } ; export -f f ; unset -f f ; env | grep -c BASH_FUNC')
+[ "$res" = "0" ] || err $LINENO
+
+# caller, FUNCNAME, BASH_SOURCE, BASH_LINENO
+
+cat << 'EOF' > /tmp/.rusty_bash_lib
+g(){
+  echo "${FUNCNAME[*]} | ${BASH_SOURCE[*]} | ${BASH_LINENO[*]}"
+  caller ; caller 0 ; caller 1 ; caller 2 ; echo $?
+}
+EOF
+cat << 'EOF' > /tmp/.rusty_bash_main
+source /tmp/.rusty_bash_lib
+f(){
+  g x y
+}
+f
+echo ${FUNCNAME-unset}
+EOF
+res=$($com /tmp/.rusty_bash_main)
+[ "$res" = "g f main | /tmp/.rusty_bash_lib /tmp/.rusty_bash_main /tmp/.rusty_bash_main | 3 5 0
+3 /tmp/.rusty_bash_main
+3 f /tmp/.rusty_bash_main
+5 main /tmp/.rusty_bash_main
+1
+unset" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extdebug ; f(){ echo "${BASH_ARGC[0]} ${BASH_ARGV[*]}" ; } ; f a b')
+[ "$res" = "2 b a" ] || err $LINENO
+
+res=$($com <<< 'caller x')
+[ "$?" = "2" ] || err $LINENO
+
+# FUNCNEST and nesting limits
+
+res=$($com <<< 'FUNCNEST=3 ; f(){ echo $1 ; f $(($1+1)) ; echo after ; } ; f 1 ; echo no')
+[ "$res" = "1
+2
+3" ] || err $LINENO
+
+res=$($com <<< 'FUNCNEST=5 ; e(){ eval e ; } ; e ; echo $?')
+[ "$res" = "1" ] || err $LINENO
+
+res=$(ulimit -s 1024 ; $com <<< 'f(){ f ; } ; f
+echo $?' 2> /dev/null)
+[ "$res" = "1" ] || err $LINENO
+
+echo 'source /tmp/.rusty_bash_self' > /tmp/.rusty_bash_self
+res=$(ulimit -s 1024 ; $com <<< 'source /tmp/.rusty_bash_self
+echo $?' 2> /dev/null)
+[ "$res" = "1" ] || err $LINENO
+
+# source
+
+cat << 'EOF' > /tmp/.rusty_bash_args
+echo "$# $1 $2"
+false
+EOF
+res=$($com <<< 'set -- x ; source /tmp/.rusty_bash_args a b ; echo $? $1 $#')
+[ "$res" = "2 a b
+1 x 1" ] || err $LINENO
+
+res=$($com <<< 'cd / ; PATH=/tmp:$PATH ; source .rusty_bash_args q')
+[ "$res" = "1 q " ] || err $LINENO
+
+res=$($com <<< 'cd / ; PATH=/tmp:$PATH ; shopt -u sourcepath ; source .rusty_bash_args q')
+[ "$?" = "1" ] || err $LINENO
+
+printf 'echo one\nif true\necho x )\necho two\n' > /tmp/.rusty_bash_bad
+res=$($com <<< 'source /tmp/.rusty_bash_bad ; echo $?' 2>&1)
+[ "$res" = "one
+/tmp/.rusty_bash_bad: line 3: syntax error near unexpected token \`)'
+2" ] || err $LINENO
+
+echo 'echo a; if' > /tmp/.rusty_bash_bad
+res=$($com <<< 'source /tmp/.rusty_bash_bad ; echo $?' 2>&1)
+[ "$res" = "/tmp/.rusty_bash_bad: line 2: syntax error: unexpected end of file
+2" ] || err $LINENO
+
+res=$($com <<< 'source')
+[ "$?" = "2" ] || err $LINENO
+
+### hash ###
+
+res=$($com <<< 'hash ; ls > /dev/null ; ls > /dev/null ; hash ; hash -t ls')
+[ "$res" = "hash: hash table empty
+hits	command
+   2	$(which ls)
+$(which ls)" ] || err $LINENO
+
+res=$($com <<< 'hash ls ; hash -l ; hash -d ls ; hash ; hash -d ls' 2>&1)
+[ "$res" = "builtin hash -p $(which ls) ls
+hash: hash table empty
+bash: hash: ls: not found" ] || err $LINENO
+
+res=$($com <<< 'hash ls ; PATH=$PATH ; hash ; hash ls ; hash -r ; hash')
+[ "$res" = "hash: hash table empty
+hash: hash table empty" ] || err $LINENO
+
+res=$($com <<< 'hash -p /bin/echo foo ; hash -t foo ls ; echo $? ; hash nosuchcommand ; echo $?' 2>&1)
+[ "$res" = "foo	/bin/echo
+bash: hash: ls: not found
+1
+bash: hash: nosuchcommand: not found
+1" ] || err $LINENO
+
+res=$($com <<< 'hash -p /nonexist/ls ls ; shopt -s checkhash ; ls -d / ; hash -t ls')
+[ "$res" = "/
+$(which ls)" ] || err $LINENO
+
+mkdir -p /tmp/.rusty_bash_nx
+echo 'echo fake' > /tmp/.rusty_bash_nx/ls
+chmod -x /tmp/.rusty_bash_nx/ls
+res=$($com <<< 'PATH=/tmp/.rusty_bash_nx:$PATH ; ls -d / ; hash -t ls')
+[ "$res" = "/
+$(which ls)" ] || err $LINENO
+rm -rf /tmp/.rusty_bash_nx
+
+res=$($com <<< 'hash -x' 2>&1)
+[ "$res" = "bash: hash: -x: invalid option
+hash: usage: hash [-lr] [-p pathname] [-dt] [name ...]" ] || err $LINENO
+
 
 echo OK $0
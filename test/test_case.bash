@@ -44,6 +44,81 @@ res=$($com <<< 'glob_test "[a-z]" "b"')
 res=$($com <<< 'glob_test "[!a-c]" "b"')
 [ "$?" = "1" ] || err $LINENO
 
+res=$($com <<< 'glob_test "[a-c]" "c"')
+[ "$?" = "0" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[]]" "]"')
+[ "$?" = "0" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[" "["')
+[ "$?" = "0" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[[:alpha:]][[:digit:]]" "a1"')
+[ "$?" = "0" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[![:lower:]]" "a"')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[^[:space:][:punct:]]" "_"')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[[=a=]][[.-.]]" "a-"')
+[ "$?" = "0" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[!]a]" "]"')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'glob_test "[[:foo:]]" "f"')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'case B in [[:upper:]]) echo ok ;; esac')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'x="[ab]" ; case a in $x) echo ok ;; esac ; case a in "$x") echo ng ;; esac')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'case b in a | b ) echo ok ;; esac')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'shopt -s nocasematch ; case ABC in a[b-c]*) echo ok ;; esac ; case A in [[:lower:]]) echo ng ;; esac')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob
+glob_test "+([0-9]).@(c|h)" "123.h"')
+[ "$?" = "0" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob
+glob_test "!(*.o)" "a.o"')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'glob_test "@(a|b)" "a"')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob
+f() { case $1 in +([0-9])) echo num ;; @(a|b)*) echo ab ;; !(*.*)) echo noext ;; *) echo other ;; esac ; }
+f 123 ; f a.o ; f zzz ; f z.z')
+[ "$res" = "num
+ab
+noext
+other" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob
+case x in @(a|+(x|y))) echo nested ;; esac ; case "a b" in @(a b|"*")) echo sp ;; esac')
+[ "$res" = "nested
+sp" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob
+x="@(a)"
+case a in "@(a)") echo ng1 ;; esac ; case a in '"'"'@(a)'"'"') echo ng2 ;; esac
+case a in @"(a)") echo ng3 ;; esac ; case a in "$x") echo ng4 ;; esac
+case "@(a)" in "@(a)") echo ok1 ;; esac ; case "a|b" in @(a"|"b)) echo ok2 ;; esac')
+[ "$res" = "ok1
+ok2" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob
+case aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaac in *(a|aa)b) echo ng ;; *(a|aa)c) echo ok ;; esac')
+[ "$res" = "ok" ] || err $LINENO
+
 res=$($com <<< 'echo a || echo b || echo c')
 [ "$res" = "a" ] || err $LINENO
 
@@ -103,4 +178,23 @@ res=$($com <<< 'case a in a) echo A ;& b) echo B ;& c) echo C ;; esac' )
 B
 C" ] || err $LINENO
 
+res=$($com <<< 'case a in b) echo n ;; a) echo A ;& c) echo B ;;& a) echo C ;;& *) echo D ;& esac' )
+[ "$res" = "A
+B
+C
+D" ] || err $LINENO
+
+res=$($com <<< 'case a in (b|a) echo A ;; esac ; case b in a) ;; (b) echo B
+esac' )
+[ "$res" = "A
+B" ] || err $LINENO
+
+res=$($com <<< 'false ; case z in a) echo n ;; esac ; echo $? ; case a in a) false ;& b) ;; esac ; echo $?' )
+[ "$res" = "0
+0" ] || err $LINENO
+
+res=$($com <<< 'case "a|b" in a|b) echo n ;; "a|b") echo A ;; esac ; case x in "*") echo n ;; '"'x'"'|y) echo B ;; esac' )
+[ "$res" = "A
+B" ] || err $LINENO
+
 echo OK $0
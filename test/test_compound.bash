@@ -112,6 +112,12 @@ res=$($com <<< 'echo $((1+2/3))')
 res=$($com <<< 'echo $((-1+2/3))')
 [ "$res" = "-1" ] || err $LINENO
 
+res=$($com <<< 'A=3 ; echo $(( A * (1 + 2) - 2 ** 3 ))')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'echo $((1/0))')
+[ "$res" = "0" ] || err $LINENO
+
 ### MULTILINE INPUT ###
 
 res=$($com << 'EOF'
@@ -171,4 +177,75 @@ EOF
 [ "$res" = "a
 b" ] || err $LINENO
 
+# break
+
+res=$($com <<< 'while true ; do while true ; do break 2 ; done ; echo ng ; done ; echo $?')
+[ "$res" = "0" ] || err $LINENO
+
+res=$($com <<< 'while true ; do if true ; then break ; fi ; echo ng ; done ; echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'f(){ break ; } ; while true ; do f ; echo a ; break ; done ; break ; echo $?' 2>&1)
+[ "$res" = "bash: break: only meaningful in a \`for', \`while', or \`until' loop
+a
+bash: break: only meaningful in a \`for', \`while', or \`until' loop
+0" ] || err $LINENO
+
+res=$($com <<< 'while true ; do break 0 ; echo ng ; done ; echo $?' 2>&1)
+[ "$res" = "bash: break: 0: loop count out of range
+1" ] || err $LINENO
+
+# select
+
+res=$(printf '2\n\n9\n' | $com -c 'select v in apple banana cherry ; do echo "$v:$REPLY" ; done ; echo $?' 2>&1)
+[ "$res" = "1) apple
+2) banana
+3) cherry
+#? banana:2
+#? 1) apple
+2) banana
+3) cherry
+#? :9
+#? 
+1" ] || err $LINENO
+
+res=$(printf ' 2 \n' | $com -c 'set -- x y ; PS3="> "
+select v
+do
+  echo "[$v][$REPLY]" ; break
+done ; echo $?' 2>/dev/null)
+[ "$res" = "[y][ 2 ]
+0" ] || err $LINENO
+
+res=$(printf '1\n' | $com -c 'select v
+in x y ; do echo $v ; break ; done' 2>/dev/null)
+[ "$res" = "x" ] || err $LINENO
+
+res=$(printf '1\n' | COLUMNS=40 $com -c 'select v in w1 w2 w3 w4 w5 w6 w7 w8 w9 ; do break ; done' 2>&1)
+[ "$res" = "1) w1  3) w3  5) w5  7) w7  9) w9
+2) w2  4) w4  6) w6  8) w8
+#? " ] || err $LINENO
+
+# coproc
+
+res=$($com -c 'coproc cat ; echo hello >&${COPROC[1]} ; read -u ${COPROC[0]} a ; echo $a' 2>/dev/null < /dev/null)
+[ "$res" = "hello" ] || err $LINENO
+
+res=$($com -c 'coproc UP { while read a ; do echo "[$a]" ; done ; } ; echo x >&${UP[1]} ; read -u ${UP[0]} b ; echo $b ${#UP[@]}' 2>/dev/null < /dev/null)
+[ "$res" = "[x] 2" ] || err $LINENO
+
+res=$($com -c 'coproc { echo hi ; } ; cat <&${COPROC[0]} ; wait ; echo ${COPROC[0]}-$COPROC_PID-' 2>/dev/null < /dev/null)
+[ "$res" = "hi
+--" ] || err $LINENO
+
+res=$($com -c 'coproc MY { read x ; echo "got $x" ; } ; echo ping >&${MY[1]} ; read -u ${MY[0]} r ; echo "$r"' 2>/dev/null < /dev/null)
+[ "$res" = "got ping" ] || err $LINENO
+
+res=$($com -c 'coproc sleep 0.1 ; fd=${COPROC[0]} ; sleep 0.5 ; echo "[${COPROC[@]}][$COPROC_PID]" ; read -u $fd a ; echo $?' 2>/dev/null < /dev/null)
+[ "$res" = "[][]
+1" ] || err $LINENO
+
+res=$($com -c 'coproc sleep 1 ; echo $COPROC_PID | grep -q "^[0-9][0-9]*$" ; echo $?' 2>/dev/null < /dev/null)
+[ "$res" = "0" ] || err $LINENO
+
 echo OK $0
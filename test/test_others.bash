@@ -18,7 +18,7 @@ cat << 'EOF' > $tmp
 echo $1 $2 $3
 EOF
 
-res=$(cat $tmp  | $com a b c)
+res=$(cat $tmp  | $com -s a b c)
 [ "$res" = "a b c" ] || err $LINENO
 
 #### ARG TEST ###
@@ -135,9 +135,79 @@ res=$($com <<< 'echo "*"')
 res=$($com <<< 'echo /')
 [ "$res" = "/" ] || err $LINENO
 
-#The following checks trivial difference between bash and this.
-#$com <<< 'echo //*' | grep -F '//' 
-#$com <<< 'echo /*////' | grep -Fv '//'
+res=$($com <<< 'echo //*')
+[ "$res" = "$(echo //*)" ] || err $LINENO
+
+res=$($com <<< 'echo /*////')
+[ "$res" = "$(echo /*////)" ] || err $LINENO
+
+mkdir -p $tmp-glob/d1 $tmp-glob/d2/sub
+touch $tmp-glob/{a,b,B,.h,'a\b','[x]','c*'}
+res=$($com <<< "cd $tmp-glob ; echo * ; echo .* ; echo */ ; echo d*/*")
+[ "$res" = "B [x] a a\b b c* d1 d2
+.h
+d1/ d2/
+d2/sub" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; echo [a-b] ; echo [!a-z]* ; echo [ ; echo \[x] ; echo [[]x] ; echo c\* ; echo no*")
+[ "$res" = "a b
+B [x]
+[
+[x]
+[x]
+c*
+no*" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; echo a\\\\* ; A=(*) ; echo \${#A[@]}")
+[ "$res" = "a\b
+8" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; echo [[:upper:][:punct:]]* ; echo '[x]' \"a?\"")
+[ "$res" = "B [x]
+[x] a?" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; shopt -s extglob
+echo !([a-z]*) ; echo @(a|b|x) ; echo +(d?)/")
+[ "$res" = "B [x]
+a b
+d1/ d2/" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; shopt -s extglob
+echo \"@(a|b)\" '@(a|b)' @\"(a|b)\" ; echo \"a(b)|c\"")
+[ "$res" = "@(a|b) @(a|b) @(a|b)
+a(b)|c" ] || err $LINENO
+
+res=$(cd $tmp-glob ; $OLDPWD/$com -c 'shopt -s extglob
+echo @(a|b) ; echo !([a-z]*)')
+[ "$res" = "a b
+B [x]" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; shopt -s nullglob ; echo zz* b ; shopt -s dotglob ; echo .* ; shopt -s nocaseglob ; echo [b]")
+[ "$res" = "b
+.h
+B b" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; shopt -s failglob ; f(){ echo zz* ; echo ng ; } ; f ; echo ng
+echo \$?")
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; GLOBIGNORE='[a-z]*:*]' ; echo *")
+[ "$res" = ".h B" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; GLOBIGNORE='*b' ; echo d2/* ; GLOBIGNORE='d?/s*' ; echo d2/*")
+[ "$res" = "d2/sub
+d2/*" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob ; shopt -s globstar ; echo ** ; echo d2/** ; echo **/ ; echo **/sub")
+[ "$res" = "B [x] a a\\b b c* d1 d2 d2/sub
+d2/ d2/sub
+d1/ d2/ d2/sub/
+d2/sub" ] || err $LINENO
+
+res=$($com <<< 'x="*" ; echo ${#x} "$x" ; x=a\\b ; echo ${#x} $x')
+[ "$res" = "1 *
+3 a\b" ] || err $LINENO
+rm -rf $tmp-glob
 
 # command substitution
 
@@ -336,6 +406,18 @@ res=$($com <<< 'a=c;echo ${a:?b}')
 res=$($com <<< '(echo ${a:?b}) 2>&1')
 [ "$res" = "bash: a: b" ] || err $LINENO
 
+res=$($com <<< 'echo "abc')
+[ "$?" = "2" ] || err $LINENO
+
+res=$($com <<< 'b= ; echo "[${a-x}][${b-x}][${b:-y}]" ; echo ${c=z} $c ; echo "[${b+p}][${a+p}]" ; (echo ${a?m}) 2>&1')
+[ "$res" = "[x][][y]
+z z
+[p][]
+bash: a: m" ] || err $LINENO
+
+res=$(FOO=bar $com <<< 'export FOO ; printenv FOO')
+[ "$res" = "bar" ] || err $LINENO
+
 res=$($com <<< 'LANG=C TZ= date -d 2000-01-01')
 [ "$res" = "Sat Jan  1 00:00:00 UTC 2000" ] || err $LINENO
 
@@ -362,9 +444,21 @@ echo "$*"
 EOF
 
 res=$($com -x <<< 'echo $-')
-[ "$res" = "x" ] || err $LINENO
+[ "$res" = "xs" ] || err $LINENO
+
+res=$($com -i <<< 'echo $-')
+[ "$res" = "ims" ] || err $LINENO
+
+res=$($com -i +m -c 'echo $-')
+[ "$res" = "i" ] || err $LINENO
 
-res=$(cat $tmp  | $com あい うえ お)
+res=$($com -i -c '[ -n "$PS1" ] && echo set')
+[ "$res" = "set" ] || err $LINENO
+
+res=$(PS1=x $com -c 'echo "[$PS1]"')
+[ "$res" = "[]" ] || err $LINENO
+
+res=$(cat $tmp  | $com -s あい うえ お)
 [ "$res" = "あい うえ お
 あい うえ お
 あい💩うえ💩お" ] || err $LINENO
@@ -373,6 +467,77 @@ res=$($com <<< 'A=x;echo a $A; echo $_')
 [ "$res" = "a x
 x" ] || err $LINENO
 
+res=$($com <<< 'set a b c d e f g h i j k ; echo ${10} $10 ${11}')
+[ "$res" = "j a0 k" ] || err $LINENO
+
+res=$($com <<< 'RANDOM=1 ; echo $RANDOM $RANDOM')
+[ "$res" = "16807 10791" ] || err $LINENO
+
+res=$($com <<< 'unset RANDOM ; echo x${RANDOM}x')
+[ "$res" = "xx" ] || err $LINENO
+
+res=$($com <<< 'SECONDS=100 ; echo $SECONDS')
+[ "$res" = "100" ] || err $LINENO
+
+res=$($com <<< '[ $EPOCHSECONDS -gt 1600000000 ] && [ -n "$SRANDOM" ] && echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< '[ "$BASHPID" = "$$" ] && [ "$(echo $BASHPID)" != "$$" ] && echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< '( [ "$BASHPID" != "$$" ] && [ "$(echo $$)" = "$$" ] && echo ok )')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'echo $BASH_SUBSHELL ; ( echo $BASH_SUBSHELL ; (echo $BASH_SUBSHELL) ) ; echo $(echo $BASH_SUBSHELL)')
+[ "$res" = "0
+1
+2
+1" ] || err $LINENO
+
+res=$($com <<< 'echo $BASH_SUBSHELL | cat ; echo $BASHPID | { read p ; [ "$p" != "$$" ] && echo ok ; }')
+[ "$res" = "0
+ok" ] || err $LINENO
+
+res=$($com <<< 'echo $(echo $BASH_SUBSHELL) | cat ; { echo $BASH_SUBSHELL ; } | cat ; cat < /dev/null | echo $BASH_SUBSHELL')
+[ "$res" = "1
+1
+0" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo $BASH_SUBSHELL $1 ; } ; f $BASH_SUBSHELL | cat ; f x & wait ; echo $(echo $BASH_SUBSHELL | cat)')
+[ "$res" = "1 0
+1 x
+1" ] || err $LINENO
+
+res=$($com <<< 'a=1 ; ( a=2 ; exit 4 ; echo no ) ; echo $? $a')
+[ "$res" = "4 1" ] || err $LINENO
+
+res=$($com <<< 'set -x ; echo a | cat ; ( echo b )' 2>&1)
+[ "$res" = "+ echo a
++ cat
+a
++ echo b
+b" ] || err $LINENO
+
+res=$($com <<< 'echo $LINENO
+echo $LINENO')
+[ "$res" = "1
+2" ] || err $LINENO
+
+res=$(bash -c "$com -c 'echo \$PPID' ; echo \$\$" | uniq | wc -l)
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'UID=3')
+[ "$?" = "1" ] || err $LINENO
+
+res=$(SHLVL=3 $com <<< 'echo $SHLVL ; printenv SHLVL')
+[ "$res" = "4
+4" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp ; cd / ; echo $OLDPWD ; cd - ; echo $PWD')
+[ "$res" = "/tmp
+/tmp
+/tmp" ] || err $LINENO
+
 ### REDIRECTION ###
 
 res=$($com << 'EOF'
@@ -575,7 +740,7 @@ echo $#
 echo $hoge
 EOF
 
-res=$(cat $tmp  | $com x y z 1 2 3)
+res=$(cat $tmp  | $com -s x y z 1 2 3)
 [ "$res" = "a b c
 3
 6
@@ -595,7 +760,7 @@ echo $#
 echo $hoge
 EOF
 
-res=$(cat $tmp  | $com x y z 1 2 3)
+res=$(cat $tmp  | $com -s x y z 1 2 3)
 [ "$res" = "a b c
 3
 6
@@ -613,7 +778,7 @@ f a b c | rev
 echo $hoge
 EOF
 
-res=$(cat $tmp  | $com x y z)
+res=$(cat $tmp  | $com -s x y z)
 [ "$res" = "c b a
 y" ] || err $LINENO
 
@@ -633,6 +798,55 @@ EOF
 res=$($com <<< 'a(){ echo x; return ; echo b ; } ; a')
 [ "$res" = "x" ] || err $LINENO
 
+res=$($com <<< 'f(){ if true ; then return 3 ; fi ; echo no ; } ; f ; echo $?')
+[ "$res" = "3" ] || err $LINENO
+
+res=$($com <<< 'f(){ while true ; do return 5 ; done ; echo no ; } ; f ; echo $?')
+[ "$res" = "5" ] || err $LINENO
+
+res=$($com <<< 'f(){ case a in a) return 7 ;; esac ; echo no ; } ; f ; echo $?')
+[ "$res" = "7" ] || err $LINENO
+
+res=$($com <<< 'f(){ return 3 && echo no ; } ; f ; echo $?')
+[ "$res" = "3" ] || err $LINENO
+
+res=$($com <<< 'f(){ g ; echo $? ; } ; g(){ false ; return ; echo no ; } ; f')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'f(){ return x ; } ; f ; echo $?')
+[ "$res" = "2" ] || err $LINENO
+
+res=$($com <<< 'f(){ if [ $1 -gt 0 ] ; then f $(($1-1)) ; fi ; echo -n $1 ; } ; f 3 ; f 2')
+[ "$res" = "0123012" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo old ; f(){ echo new ; } ; f ; } ; f ; f')
+[ "$res" = "old
+new
+new" ] || err $LINENO
+
+res=$($com <<< 'return ; echo $?')
+[ "$res" = "1" ] || err $LINENO
+
+printf 'echo in\nreturn 4\necho no\n' > $tmp-return
+res=$($com <<< "source $tmp-return ; echo \$?")
+[ "$res" = "in
+4" ] || err $LINENO
+rm -f $tmp-return
+
+res=$($com <<< 'f(){ exit 9 ; } ; f | cat ; echo $? ; ( f ) ; echo $? ; exit 300')
+[ "$?" = "44" ] || err $LINENO
+[ "$res" = "0
+9" ] || err $LINENO
+
+res=$($com <<< 'f () { if [ "$1" != xx ]; then f x$1; else echo end; fi; echo $1; } ; f x')
+[ "$res" = "end
+xx
+x" ] || err $LINENO
+
+res=$($com <<< 'f () { echo a; f () { echo b; } ; } ; f ; f')
+[ "$res" = "a
+b" ] || err $LINENO
+
 ### OR and AND ###
 
 res=$($com <<< 'echo a || echo b || echo c')
@@ -653,6 +867,88 @@ res=$($com <<< 'seq 3 | while read x ; do echo $x🎂 ; done')
 2🎂
 3🎂" ] || err $LINENO
 
+### XTRACE ###
+
+res=$($com <<< 'set -x; A="a b"; echo $A c; set +x; echo d' 2>&1)
+[ "$res" = "+ A='a b'
++ echo 'a b' c
+a b c
++ set +x
+d" ] || err $LINENO
+
+res=$($com -x <<< 'echo $(echo a)' 2>&1)
+[ "$res" = "++ echo a
++ echo a
+a" ] || err $LINENO
+
+res=$($com <<< 'PS4="> "; set -x; ((1+2)); case x in x) ;; esac' 2>&1)
+[ "$res" = "> (( 1+2 ))
+> case x in" ] || err $LINENO
+
+res=$($com <<< 'BASH_XTRACEFD=1; set -x; true' 2> /dev/null)
+[ "$res" = "+ true" ] || err $LINENO
+
+### INVOCATION ###
+
+res=$($com -c 'echo $0 $1 $#' name a b)
+[ "$res" = "name a 2" ] || err $LINENO
+
+res=$($com -c 'echo $0 $1' -- x)
+[ "$res" = "-- x" ] || err $LINENO
+
+res=$($com -s a b <<< 'echo $1 $#')
+[ "$res" = "a 2" ] || err $LINENO
+
+res=$($com -o xtrace -c 'echo $-' 2> /dev/null)
+[ "$res" = "x" ] || err $LINENO
+
+res=$($com --norc -c 'exit 3')
+[ "$?" = "3" ] || err $LINENO
+
+cat << 'EOF' > $tmp 
+echo $0 $@
+EOF
+
+res=$($com $tmp a b )
+[ "$res" = "$tmp a b" ] || err $LINENO
+
+res=$($com /tmp/nonexistent_file_$$ 2> /dev/null)
+[ "$?" = "127" ] || err $LINENO
+
+### STARTUP FILES ###
+
+mkdir -p $tmp.home
+echo 'echo profile' > $tmp.home/.profile
+echo 'echo logout' > $tmp.home/.bash_logout
+echo 'echo rc' > $tmp.home/.rusty_bashrc
+echo 'echo env' > $tmp.home/env
+
+res=$(HOME=$tmp.home $com -l -c 'echo cmd' 2> /dev/null | tail -n 3)
+[ "$res" = "profile
+cmd
+logout" ] || err $LINENO
+
+res=$(HOME=$tmp.home $com -l --noprofile -c 'exit 3')
+[ "$?-$res" = "3-logout" ] || err $LINENO
+
+printf 'echo out1\nexit 7\necho out2\n' > $tmp.home/.bash_logout
+res=$(HOME=$tmp.home $com -l --noprofile -c 'exit 5')
+[ "$?-$res" = "7-out1" ] || err $LINENO
+echo 'echo logout' > $tmp.home/.bash_logout
+
+res=$(HOME=$tmp.home $com -i -c 'echo cmd')
+[ "$res" = "rc
+cmd" ] || err $LINENO
+
+res=$(HOME=$tmp.home $com -i --norc -c 'echo cmd')
+[ "$res" = "cmd" ] || err $LINENO
+
+res=$(HOME=$tmp.home BASH_ENV='$HOME/env' $com -c 'echo cmd')
+[ "$res" = "env
+cmd" ] || err $LINENO
+
+rm -rf $tmp.home
+
 ### MISC ###
 
 cat << EOF > $tmp 
@@ -671,4 +967,33 @@ chmod +x $tmp
 res=$($tmp )
 [ "$res" = "hoge" ] || err $LINENO
 
+### time ###
+
+res=$($com <<< 'time -p true' 2>&1 | sed 's/[0-9]/N/g')
+[ "$res" = "real N.NN
+user N.NN
+sys N.NN" ] || err $LINENO
+
+res=$($com <<< 'time true' 2>&1 | sed 's/[0-9]/N/g')
+[ "$res" = "
+real	NmN.NNNs
+user	NmN.NNNs
+sys	NmN.NNNs" ] || err $LINENO
+
+res=$($com <<< 'TIMEFORMAT="%R:%1U:%%"; time true' 2>&1 | sed 's/[0-9]/N/g')
+[ "$res" = "N.NNN:N.N:%" ] || err $LINENO
+
+res=$($com <<< 'TIMEFORMAT=; time echo a' 2>&1)
+[ "$res" = "a" ] || err $LINENO
+
+res=$($com <<< 'TIMEFORMAT="%x"; time true' 2>&1)
+[ "$res" = "bash: TIMEFORMAT: \`x': invalid format character" ] || err $LINENO
+
+res=$($com <<< 'time ! false; echo $?' 2>/dev/null)
+[ "$res" = "0" ] || err $LINENO
+
+res=$($com <<< 'times' | sed 's/[0-9]/N/g')
+[ "$res" = "NmN.NNNs NmN.NNNs
+NmN.NNNs NmN.NNNs" ] || err $LINENO
+
 echo OK $0